@@ -1,14 +1,39 @@
 use proc_macro2::Ident;
 use quote::quote;
 use syn::parse::{Parse, Result};
-use syn::{Attribute, Lit, LitInt, Pat, Type};
+use syn::{Attribute, Expr, Lit, LitInt, LitStr, Pat, Path, Type};
 
 /// Available endiannesses
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum Endianness {
     Big,
     Little,
     Native,
+    /// chosen at runtime with a `plod::ByteOrder`
+    Dynamic,
+}
+
+/// Available checksum algorithms, with the same name as `plod::__private::Checksum` variants in snake case
+pub const CHECKSUMS: [&str; 8] = [
+    "crc32",
+    "crc32c",
+    "crc16_arc",
+    "crc16_modbus",
+    "crc16_ccitt",
+    "crc16_xmodem",
+    "internet",
+    "adler32",
+];
+
+/// Available string encodings, `Utf16` follows the endianness
+#[derive(Clone, Copy, PartialEq)]
+pub enum TextEncoding {
+    Utf8,
+    Ascii,
+    Latin1,
+    Utf16,
+    Utf16Be,
+    Utf16Le,
 }
 
 /// Attributes that can be used with derive, all in one structure to make it easier to parse and inherit.
@@ -22,12 +47,68 @@ pub struct Attributes {
     pub keep_tag: bool,
     /// is the above retained different from the tag (how much less)
     pub keep_diff: Option<LitInt>,
+    /// this variant catches all unknown tags and keeps the rest of the data (not inherited)
+    pub other: bool,
+    /// the enum tag is stored in an earlier field instead of a prefix (not inherited)
+    pub tag_from: Option<Ident>,
+    /// variants are generated without their tag, for `PlodTagged`
+    pub untagged: bool,
     /// type of the vector size storage
     pub size_type: Option<Ident>,
     /// is the vector size counted in items or in bytes
     pub byte_sized: bool,
     /// Size is off by one
     pub size_is_next: bool,
+    /// vector size is stored in an earlier field instead of a prefix (not inherited)
+    pub size_from: Option<Ident>,
+    /// vector or slice contains everything until the end of data (not inherited)
+    pub rest: bool,
+    /// vector ends with this value instead of having a size (not inherited)
+    pub terminator: Option<Expr>,
+    /// strings are null terminated
+    pub cstr: bool,
+    /// size of a fixed width string (not inherited)
+    pub fixed: Option<LitInt>,
+    /// encoding of strings
+    pub encoding: TextEncoding,
+    /// replace invalid characters in strings instead of failing
+    pub lossy: bool,
+    /// the optional field is present only if this condition is true (not inherited)
+    pub condition: Option<Expr>,
+    /// the optional field is prefixed by a presence marker of this type (not inherited)
+    pub presence: Option<Ident>,
+    /// value of the presence marker when the field is present (not inherited)
+    pub present: Option<Lit>,
+    /// value of the presence marker when the field is absent (not inherited)
+    pub absent: Option<Lit>,
+    /// the field is a checksum computed with this algorithm (not inherited)
+    pub checksum: Option<Ident>,
+    /// first field covered by the checksum (not inherited)
+    pub checksum_from: Option<Ident>,
+    /// last field covered by the checksum (not inherited)
+    pub checksum_to: Option<Ident>,
+    /// check the checksum on read (not inherited)
+    pub verify: bool,
+    /// the field is the size in bytes of this later field (not inherited)
+    pub len_of: Option<Ident>,
+    /// the field is the number of items of this later field (not inherited)
+    pub count_of: Option<Ident>,
+    /// the field is the size in bytes of the struct or variant that contains it (not inherited)
+    pub size_of_self: bool,
+    /// type stored, the field is converted from and to it (not inherited)
+    pub wire: Option<Ident>,
+    /// the field is converted from the wire type with `TryFrom` instead of `From` (not inherited)
+    pub try_from: bool,
+    /// the field is converted to the wire type with `TryInto` instead of `Into` (not inherited)
+    pub try_into: bool,
+    /// module that contains `read`, `write` and `size` functions for the field (not inherited)
+    pub with: Option<Path>,
+    /// function that reads the field (not inherited)
+    pub read_with: Option<Path>,
+    /// function that writes the field (not inherited)
+    pub write_with: Option<Path>,
+    /// function that computes the size of the field (not inherited)
+    pub size_with: Option<Path>,
     /// endianness of the struct
     pub endianness: Endianness,
     /// magic type and value for this item
@@ -40,6 +121,24 @@ pub struct Attributes {
     pub is_context: bool,
     /// do not generate position handling code
     pub no_pos: bool,
+    /// implement PlodBorrow instead of Plod
+    pub borrow: bool,
+    /// also implement AsyncPlod
+    pub is_async: bool,
+    /// alignment of this item (not inherited)
+    pub align: Option<LitInt>,
+    /// number of padding bytes before this item (not inherited)
+    pub pad: Option<LitInt>,
+    /// value of padding bytes
+    pub fill: Option<LitInt>,
+    /// check padding bytes value on read
+    pub check_padding: bool,
+    /// insert padding like a C compiler (not inherited)
+    pub repr_c: bool,
+    /// number of bits of a bitfield (not inherited)
+    pub bits: Option<LitInt>,
+    /// bitfields are stored from the least significant bit
+    pub lsb_first: bool,
 }
 
 impl Default for Attributes {
@@ -49,21 +148,68 @@ impl Default for Attributes {
             tag: None,
             keep_tag: false,
             keep_diff: None,
+            other: false,
+            tag_from: None,
+            untagged: false,
             size_type: None,
             byte_sized: false,
             size_is_next: false,
+            size_from: None,
+            rest: false,
+            terminator: None,
+            cstr: false,
+            fixed: None,
+            encoding: TextEncoding::Utf8,
+            lossy: false,
+            condition: None,
+            presence: None,
+            present: None,
+            absent: None,
+            checksum: None,
+            checksum_from: None,
+            checksum_to: None,
+            verify: true,
+            len_of: None,
+            count_of: None,
+            size_of_self: false,
+            wire: None,
+            try_from: false,
+            try_into: false,
+            with: None,
+            read_with: None,
+            write_with: None,
+            size_with: None,
             endianness: Endianness::Native,
             magic: None,
             skip: false,
             context_type: Type::Verbatim(quote! { () }),
             is_context: false,
             no_pos: false,
+            borrow: false,
+            is_async: false,
+            align: None,
+            pad: None,
+            fill: None,
+            check_padding: false,
+            repr_c: false,
+            bits: None,
+            lsb_first: false,
         }
     }
 }
 
 /// A single Attribute structure makes it easier to write parsing code but give worse error reporting
 impl Attributes {
+    /// Does this item define how its size is stored, without inheritance
+    pub fn has_own_size(attrs: &Vec<Attribute>) -> Result<bool> {
+        let own = Attributes::parse(attrs)?;
+        Ok(own.size_type.is_some()
+            || own.size_from.is_some()
+            || own.terminator.is_some()
+            || own.fixed.is_some()
+            || own.cstr)
+    }
+
     /// Get structure or enum attributes dedicated to this derive
     pub fn parse(attrs: &Vec<Attribute>) -> Result<Self> {
         let mut result = Attributes::default();
@@ -93,16 +239,156 @@ impl Attributes {
                     self.endianness = Endianness::Little;
                 } else if meta.path.is_ident("native_endian") {
                     self.endianness = Endianness::Native;
+                } else if meta.path.is_ident("endian") {
+                    let ident = Ident::parse(meta.value()?)?;
+                    self.endianness = match ident.to_string().as_str() {
+                        "big" => Endianness::Big,
+                        "little" => Endianness::Little,
+                        "native" => Endianness::Native,
+                        "dynamic" => Endianness::Dynamic,
+                        _ => {
+                            return Err(syn::Error::new(
+                                ident.span(),
+                                "Supported endians are big, little, native and dynamic",
+                            ))
+                        }
+                    };
                 } else if meta.path.is_ident("mo_pos") {
                     self.no_pos = true;
                 } else if meta.path.is_ident("keep_tag") {
                     self.keep_tag = true;
+                } else if meta.path.is_ident("other") {
+                    self.other = true;
+                    self.keep_tag = true;
                 } else if meta.path.is_ident("byte_sized") {
                     self.byte_sized = true;
                 } else if meta.path.is_ident("size_is_next") {
                     self.size_is_next = true;
                 } else if meta.path.is_ident("skip") {
                     self.skip = true;
+                } else if meta.path.is_ident("align") {
+                    let lit = LitInt::parse(meta.value()?)?;
+                    if lit.base10_parse::<usize>()? == 0 {
+                        return Err(syn::Error::new(lit.span(), "Alignment cannot be 0"));
+                    }
+                    self.align = Some(lit);
+                } else if meta.path.is_ident("pad") {
+                    self.pad = Some(LitInt::parse(meta.value()?)?);
+                } else if meta.path.is_ident("fill") {
+                    let lit = LitInt::parse(meta.value()?)?;
+                    lit.base10_parse::<u8>()?;
+                    self.fill = Some(lit);
+                } else if meta.path.is_ident("rest") {
+                    self.rest = true;
+                } else if meta.path.is_ident("terminator") {
+                    self.terminator = Some(Expr::parse(meta.value()?)?);
+                } else if meta.path.is_ident("if") {
+                    // the condition can be given as a string or directly as an expression
+                    let value = meta.value()?;
+                    let condition = if value.peek(LitStr) {
+                        value.parse::<LitStr>()?.parse()?
+                    } else {
+                        Expr::parse(value)?
+                    };
+                    self.condition = Some(condition);
+                } else if meta.path.is_ident("presence") {
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("present") {
+                            self.present = Some(Lit::parse(meta.value()?)?);
+                        } else if meta.path.is_ident("absent") {
+                            self.absent = Some(Lit::parse(meta.value()?)?);
+                        } else {
+                            self.presence = meta.path.get_ident().cloned();
+                        }
+                        Ok(())
+                    })?;
+                } else if meta.path.is_ident("cstr") {
+                    self.cstr = true;
+                } else if meta.path.is_ident("fixed") {
+                    let lit = LitInt::parse(meta.value()?)?;
+                    lit.base10_parse::<usize>()?;
+                    self.fixed = Some(lit);
+                } else if meta.path.is_ident("encoding") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    self.encoding = match lit.value().as_str() {
+                        "utf8" => TextEncoding::Utf8,
+                        "ascii" => TextEncoding::Ascii,
+                        "latin1" => TextEncoding::Latin1,
+                        "utf16" => TextEncoding::Utf16,
+                        "utf16be" => TextEncoding::Utf16Be,
+                        "utf16le" => TextEncoding::Utf16Le,
+                        _ => {
+                            return Err(syn::Error::new(
+                                lit.span(),
+                                "Supported encodings are utf8, ascii, latin1, utf16, utf16be and utf16le",
+                            ))
+                        }
+                    };
+                } else if meta.path.is_ident("lossy") {
+                    self.lossy = true;
+                } else if meta.path.is_ident("checksum") {
+                    let ident = Ident::parse(meta.value()?)?;
+                    if !CHECKSUMS.contains(&ident.to_string().as_str()) {
+                        return Err(syn::Error::new(
+                            ident.span(),
+                            format!("Supported checksums are {}", CHECKSUMS.join(", ")),
+                        ));
+                    }
+                    self.checksum = Some(ident);
+                } else if meta.path.is_ident("from") {
+                    self.checksum_from = Some(Ident::parse(meta.value()?)?);
+                } else if meta.path.is_ident("to") {
+                    self.checksum_to = Some(Ident::parse(meta.value()?)?);
+                } else if meta.path.is_ident("verify") {
+                    self.verify = syn::LitBool::parse(meta.value()?)?.value;
+                } else if meta.path.is_ident("len_of") {
+                    self.len_of = Some(Ident::parse(meta.value()?)?);
+                } else if meta.path.is_ident("count_of") {
+                    self.count_of = Some(Ident::parse(meta.value()?)?);
+                } else if meta.path.is_ident("size_of_self") {
+                    self.size_of_self = true;
+                } else if meta.path.is_ident("wire") {
+                    self.wire = Some(Ident::parse(meta.value()?)?);
+                } else if meta.path.is_ident("try_from") {
+                    self.try_from = true;
+                } else if meta.path.is_ident("into") {
+                    self.try_into = false;
+                } else if meta.path.is_ident("try_into") {
+                    self.try_into = true;
+                } else if meta.path.is_ident("with") {
+                    self.with = Some(Path::parse(meta.value()?)?);
+                } else if meta.path.is_ident("read_with") {
+                    self.read_with = Some(Path::parse(meta.value()?)?);
+                } else if meta.path.is_ident("write_with") {
+                    self.write_with = Some(Path::parse(meta.value()?)?);
+                } else if meta.path.is_ident("size_with") {
+                    self.size_with = Some(Path::parse(meta.value()?)?);
+                } else if meta.path.is_ident("tag_from") {
+                    self.tag_from = Some(Ident::parse(meta.value()?)?);
+                } else if meta.path.is_ident("count_from") {
+                    self.size_from = Some(Ident::parse(meta.value()?)?);
+                    self.byte_sized = false;
+                } else if meta.path.is_ident("bytes_from") {
+                    self.size_from = Some(Ident::parse(meta.value()?)?);
+                    self.byte_sized = true;
+                } else if meta.path.is_ident("bits") {
+                    let lit = LitInt::parse(meta.value()?)?;
+                    if lit.base10_parse::<u32>()? == 0 {
+                        return Err(syn::Error::new(lit.span(), "A bitfield cannot be empty"));
+                    }
+                    self.bits = Some(lit);
+                } else if meta.path.is_ident("msb_first") {
+                    self.lsb_first = false;
+                } else if meta.path.is_ident("lsb_first") {
+                    self.lsb_first = true;
+                } else if meta.path.is_ident("repr_c") {
+                    self.repr_c = true;
+                } else if meta.path.is_ident("check_padding") {
+                    self.check_padding = true;
+                } else if meta.path.is_ident("borrow") {
+                    self.borrow = true;
+                } else if meta.path.is_ident("async") {
+                    self.is_async = true;
                 } else if meta.path.is_ident("is_context") {
                     self.is_context = true;
                 } else if meta.path.is_ident("magic") {
@@ -140,7 +426,43 @@ impl Attributes {
         // reset non-inherited attributes
         result.magic = None;
         result.is_context = false;
+        result.align = None;
+        result.pad = None;
+        result.repr_c = false;
+        result.bits = None;
+        result.size_from = None;
+        result.tag_from = None;
+        result.other = false;
+        result.checksum = None;
+        result.checksum_from = None;
+        result.checksum_to = None;
+        result.verify = true;
+        result.len_of = None;
+        result.count_of = None;
+        result.size_of_self = false;
+        result.wire = None;
+        result.try_from = false;
+        result.try_into = false;
+        result.with = None;
+        result.read_with = None;
+        result.write_with = None;
+        result.size_with = None;
+        result.rest = false;
+        result.terminator = None;
+        result.fixed = None;
+        result.condition = None;
+        result.presence = None;
+        result.present = None;
+        result.absent = None;
         result._parse(attrs)?;
+        // the byte_order variable only exists in dynamic types
+        if result.endianness == Endianness::Dynamic && self.endianness != Endianness::Dynamic {
+            let attribute = attrs.iter().find(|a| a.path().is_ident("plod")).unwrap();
+            return Err(syn::Error::new_spanned(
+                attribute,
+                "#[plod(endian = dynamic)] is only supported on the whole type",
+            ));
+        }
         Ok(result)
     }
 }
@@ -7,19 +7,19 @@
 #![deny(missing_docs)]
 
 use proc_macro2::{Ident, TokenStream};
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::parse::Result;
 use syn::spanned::Spanned;
 use syn::{
-    parse_macro_input, Data, DataEnum, DeriveInput, Fields, GenericArgument, Pat, PathArguments,
-    Type, TypePath,
+    parse_macro_input, Data, DataEnum, DeriveInput, Field, Fields, GenericArgument, Generics, Pat,
+    Path, PathArguments, Type, TypePath, WhereClause,
 };
 
 use proc_macro2::Span;
 use syn::LitInt;
 
 mod attributes;
-use attributes::{Attributes, Endianness};
+use attributes::{Attributes, Endianness, TextEncoding};
 
 /// produces a token stream of error to warn the final user of the error
 macro_rules! unwrap {
@@ -50,6 +50,16 @@ fn primitive_type(ty: &Ident) -> bool {
     .any(|i| ty == i)
 }
 
+/// Size in bytes of the value computed by a checksum algorithm
+fn checksum_size(algorithm: &Ident) -> usize {
+    let name = algorithm.to_string();
+    if name.starts_with("crc16") || name == "internet" {
+        2
+    } else {
+        4
+    }
+}
+
 /// We could use `core::mem::size_of` but this is more readable when debugging generated code
 fn primitive_size(ty: &Ident) -> LitInt {
     [
@@ -83,6 +93,8 @@ fn primitive_function(endianness: Endianness) -> (Ident, Ident) {
         Endianness::Big => "be",
         Endianness::Little => "le",
         Endianness::Native => "ne",
+        // branches are generated by from_bytes and to_bytes
+        Endianness::Dynamic => unreachable!("dynamic endianness has no method"),
     };
     (
         Ident::new(&format!("from_{}_bytes", en), Span::call_site()),
@@ -90,23 +102,223 @@ fn primitive_function(endianness: Endianness) -> (Ident, Ident) {
     )
 }
 
+/// Code that converts `buffer` into a primitive `ty`, dynamic endianness depends on `byte_order`
+fn from_bytes(endianness: Endianness, ty: &Ident, buffer: TokenStream) -> TokenStream {
+    match endianness {
+        Endianness::Dynamic => quote! {
+            match byte_order {
+                plod::ByteOrder::Big => #ty::from_be_bytes(#buffer),
+                plod::ByteOrder::Little => #ty::from_le_bytes(#buffer),
+            }
+        },
+        _ => {
+            let (from_method, _) = primitive_function(endianness);
+            quote! { #ty::#from_method(#buffer) }
+        }
+    }
+}
+
+/// Code that converts a primitive `value` into bytes, dynamic endianness depends on `byte_order`
+fn to_bytes(endianness: Endianness, value: TokenStream) -> TokenStream {
+    match endianness {
+        Endianness::Dynamic => quote! {
+            match byte_order {
+                plod::ByteOrder::Big => #value.to_be_bytes(),
+                plod::ByteOrder::Little => #value.to_le_bytes(),
+            }
+        },
+        _ => {
+            let (_, to_method) = primitive_function(endianness);
+            quote! { #value.#to_method() }
+        }
+    }
+}
+
 fn syn_error<S: Spanned, T>(span: &S, message: &str) -> Result<T> {
     Err(syn::Error::new(span.span(), message))
 }
 
+/// Generated code is either synchronous (`Plod`), asynchronous (`AsyncPlod`) or borrowed
+/// (`PlodBorrow`), size code is the same.
+/// Borrowed code reads from `from: &mut &'a [u8]` and has no write code.
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Sync,
+    Async,
+    Borrow,
+}
+
+impl Mode {
+    /// Fill `buffer` from the reader `from`
+    fn read_exact(self, buffer: TokenStream) -> TokenStream {
+        match self {
+            // works on the generic reader and on slices without importing a trait
+            Mode::Sync | Mode::Borrow => quote! { plod::__private::read_exact(from, #buffer) },
+            Mode::Async => quote! { plod::__private::AsyncReadExt::read_exact(from, #buffer).await },
+        }
+    }
+
+    /// Read everything left in the reader `from` and append it to `buffer`
+    fn read_to_end(self, buffer: TokenStream) -> TokenStream {
+        match self {
+            Mode::Sync | Mode::Borrow => quote! { plod::__private::read_to_end(from, #buffer) },
+            Mode::Async => quote! { plod::__private::AsyncReadExt::read_to_end(from, #buffer).await },
+        }
+    }
+
+    /// Read at most `size` bytes from the reader `from` and append them to `buffer`
+    fn read_up_to(self, buffer: TokenStream, size: TokenStream) -> TokenStream {
+        match self {
+            Mode::Sync | Mode::Borrow => quote! { plod::__private::read_up_to(from, #buffer, #size) },
+            Mode::Async => quote! {
+                plod::__private::AsyncReadExt::read_to_end(
+                    &mut plod::__private::AsyncReadExt::take(&mut *from, #size as u64),
+                    #buffer,
+                ).await
+            },
+        }
+    }
+
+    /// Write all of `buffer` to the writer `to`
+    fn write_all(self, buffer: TokenStream) -> TokenStream {
+        match self {
+            Mode::Sync | Mode::Borrow => quote! { plod::__private::write_all(to, #buffer) },
+            Mode::Async => quote! { plod::__private::AsyncWriteExt::write_all(to, #buffer).await },
+        }
+    }
+
+    /// Read a type implementing `Plod` from the reader `from`
+    fn read_item<T: quote::ToTokens>(self, ty: &T, ctx: TokenStream) -> TokenStream {
+        match self {
+            Mode::Sync | Mode::Borrow => quote! { <#ty as plod::Plod>::impl_read_from(from, #ctx, _pos) },
+            Mode::Async => quote! { <#ty as plod::AsyncPlod>::impl_read_from_async(from, #ctx, _pos).await },
+        }
+    }
+
+    /// Write a type implementing `Plod` to the writer `to`
+    fn write_item<T: quote::ToTokens>(self, ty: &T, value: &TokenStream, ctx: TokenStream) -> TokenStream {
+        match self {
+            Mode::Sync | Mode::Borrow => quote! { <#ty as plod::Plod>::impl_write_to(#value, to, #ctx, _pos) },
+            Mode::Async => quote! { <#ty as plod::AsyncPlod>::impl_write_to_async(#value, to, #ctx, _pos).await },
+        }
+    }
+
+    /// Read a value with a custom function, with `#[plod(async)]` the async function has the same
+    /// path with an `_async` suffix
+    fn read_with(self, function: &Path, ctx: &TokenStream) -> TokenStream {
+        match self {
+            Mode::Sync | Mode::Borrow => quote! { #function(from, #ctx, _pos) },
+            Mode::Async => {
+                let function = async_path(function);
+                quote! { #function(from, #ctx, _pos).await }
+            }
+        }
+    }
+
+    /// Write a value with a custom function, with `#[plod(async)]` the async function has the same
+    /// path with an `_async` suffix
+    fn write_with(self, function: &Path, value: &TokenStream, ctx: &TokenStream) -> TokenStream {
+        match self {
+            Mode::Sync | Mode::Borrow => quote! { #function(#value, to, #ctx, _pos) },
+            Mode::Async => {
+                let function = async_path(function);
+                quote! { #function(#value, to, #ctx, _pos).await }
+            }
+        }
+    }
+
+    /// Read an enum implementing `PlodTagged` whose tag has already been read
+    fn read_untagged<T: quote::ToTokens>(self, ty: &T, tag: &TokenStream, ctx: TokenStream) -> TokenStream {
+        match self {
+            Mode::Sync | Mode::Borrow => quote! { <#ty as plod::PlodTagged>::impl_read_untagged(#tag, from, #ctx, _pos) },
+            Mode::Async => quote! { <#ty as plod::AsyncPlodTagged>::impl_read_untagged_async(#tag, from, #ctx, _pos).await },
+        }
+    }
+
+    /// Write an enum implementing `PlodTagged` without its tag
+    fn write_untagged<T: quote::ToTokens>(self, ty: &T, value: &TokenStream, tag: &TokenStream, ctx: TokenStream) -> TokenStream {
+        match self {
+            Mode::Sync | Mode::Borrow => quote! { <#ty as plod::PlodTagged>::impl_write_untagged(#value, #tag, to, #ctx, _pos) },
+            Mode::Async => quote! { <#ty as plod::AsyncPlodTagged>::impl_write_untagged_async(#value, #tag, to, #ctx, _pos).await },
+        }
+    }
+}
+
+/// Async version of a custom function, `module::read` becomes `module::read_async`
+fn async_path(function: &Path) -> Path {
+    let mut function = function.clone();
+    // a parsed path has at least one segment
+    let last = function.segments.last_mut().unwrap();
+    last.ident = format_ident!("{}_async", last.ident);
+    function
+}
+
+/// Does this type have a lifetime parameter, borrowed types use `PlodBorrow` instead of `Plod`
+fn has_lifetime(type_path: &TypePath) -> bool {
+    type_path.path.segments.iter().any(|segment| match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .any(|arg| matches!(arg, GenericArgument::Lifetime(_))),
+        _ => false,
+    })
+}
+
+/// Is this type `u8`, bytes can be read and written all at once
+fn is_u8(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path.path.is_ident("u8"),
+        _ => false,
+    }
+}
+
+/// Convert a read or write error into a `plod::Error` located at the current position and path.
+/// `error_path` is a list of `.in_xxx()` calls, from the innermost item to the outermost type.
+fn map_error(error_path: &TokenStream) -> TokenStream {
+    quote! { .map_err(|e| plod::Error::from(e).at(_pos) #error_path)? }
+}
+
 /// The main derive method, plod derive is based on obvious plain old data mapping plus some
 /// options provided with `#[plod(..)]` attributes.
 ///
+/// Lifetimes and const generics are supported. Each type parameter `T` is bounded by `T: Plod`
+/// and by `&T::Context: From<&Context>`, since fields get their context with `into()`.
+///
 /// Attributes can be inherited, which means that if you define a `#[plod(size_type(u8))]` attribute
 /// on a struct, all `Vec` inside this struct will have their size stored as a `u8`;
 ///
 /// Per type attributes:
 /// - `#[plod(<endianness>)]` (default: `native_endian`), available values: `native_endian`,
-///   `big_endian`, `little_endian`.
+///   `big_endian`, `little_endian`. It can also be written `#[plod(endian = <value>)]` with `big`,
+///   `little`, `native` or `dynamic`.
+/// - `#[plod(endian = dynamic)]`: the byte order is chosen at runtime, it is converted from the
+///   context with `plod::ByteOrder::from(ctx)`, so the context can be a `plod::ByteOrder` or any type
+///   that converts to it. With a `magic(..)` on the type, the context must be `plod::ByteOrder`: a
+///   magic value found in the other byte order swaps it, like the pcap magic number, and the
+///   following fields get the detected `plod::ByteOrder` as their context. Only a magic value read
+///   with its bytes reversed is detected, distinct markers like TIFF `II` and `MM` are not. The
+///   detected byte order is not stored, writing uses the one from the context.
 /// - `#[plod(<context_type>)]` (default: `()`): the associated type to use when reading and writing data.
 ///   A context can help when reading and writing data structures.
 /// - `#[plod(no_pos)]` (default: `false`): do no generate position handling code used for alignment
 /// and padding, it makes slightly shorter code but padding in inner types won't work.
+/// - `#[plod(async)]` (default: `false`, needs the `async` feature of plod): also implement
+///   `AsyncPlod`, and `AsyncPlodTagged` for enums. Every field type must implement `AsyncPlod`, so
+///   nested derived types need this attribute too.
+/// - `#[plod(borrow)]` (default: `false`): implement `PlodBorrow<'a>` instead of `Plod`, the type
+///   must have a lifetime parameter `'a`. Fields can then be `&'a [u8]` or `&'a str` (with a
+///   `size_type`), `&'a [u8; N]` or other borrowed types, they point into the parsed slice.
+/// - `#[plod(align=<integer>)]`: the type starts and ends at a position that is a multiple of
+///   `<integer>` bytes, padding is added before and after it if needed.
+/// - `#[plod(repr_c)]` (default: `false`, structs only): padding is inserted before each field
+///   according to its natural alignment (`Plod::ALIGNMENT`) and at the end of the struct, so
+///   that it is stored like the same `#[repr(C)]` struct. Tuples are not padded internally.
+///   Nested structs must also be `repr_c` to be laid out like `#[repr(C)]`, without it they are
+///   stored like `#[repr(C, packed)]` structs and their `ALIGNMENT` is 1 unless they have `align`.
+/// - `#[plod(fill=<byte>)]` (default: `0`): value of padding bytes written.
+/// - `#[plod(check_padding)]` (default: `false`): padding bytes are checked on read, an error of
+///   kind `BadPadding` is returned if they are not equal to the `fill` value. They are ignored
+///   otherwise.
 ///
 /// Enum specific attributes:
 /// - `#[plod(tag_type(<tag_type>))]` defines the type used to store the enum discriminant. This must be a
@@ -125,8 +337,15 @@ fn syn_error<S: Spanned, T>(span: &S, message: &str) -> Result<T> {
 ///   combination with a tag value that is a range. Eg: `#[plod(tag=6..=8, keep_diff=6)]` will
 ///   store a value between 0 and 2 included in the first field of this variant when a value
 ///   between 6 and 8 is encountered during the read.
-/// - `#[plod(skip)]` the variant is ignored, it is not created and produces an error of kind Other
-///   if encountered during write
+/// - `#[plod(skip)]` the variant is ignored, it is not created and produces an error of kind
+///   `SkippedVariant` if encountered during write
+/// - `#[plod(other)]` (implies `keep_tag`) the variant catches all tags that no other variant
+///   matches, eg: `#[plod(other)] Unknown { tag: u8, raw: Vec<u8> }`. Unless it has its own size
+///   attribute like `size_type`, its last field keeps everything left in the data like `rest` when
+///   it can have a size, so that unknown records are written back byte for byte. It must be the
+///   last variant.
+///   When such an enum is read with `tag_from` and other fields follow it, use `bytes_from` on
+///   the enum field, otherwise the last field also takes the bytes of the following fields.
 ///
 /// Field item specific attributes:
 /// - `#[plod(magic(<type>=<value>))]` the field will be prefixed by a magic value. This value must be present
@@ -135,14 +354,102 @@ fn syn_error<S: Spanned, T>(span: &S, message: &str) -> Result<T> {
 ///   to be created on deserialization.
 /// - `#[plod(is_context)]` (default: false): this field will be used as the context for all next fields
 ///   encountered in this structure.
+/// - `#[plod(bits=<integer>)]`: the field is a bitfield of `<integer>` bits, it must be an integer
+///   or a `bool` (1 bit). Consecutive bitfields are packed into a storage unit that has the type of
+///   the first one (`u8` for `bool`) and its endianness, they must fill it exactly. Signed values
+///   use two's complement, values that don't fit produce an error of kind `BitfieldOverflow` on write.
+/// - `#[plod(<bit_order>)]` (default: `msb_first`), available values: `msb_first`, `lsb_first`:
+///   bitfields are packed from the most or from the least significant bit of their storage unit.
+/// - `#[plod(pad=<integer>)]`: `<integer>` padding bytes are inserted before the field.
+/// - `#[plod(align=<integer>)]`: padding bytes are inserted before the field so that it starts at
+///   a position that is a multiple of `<integer>` bytes. Positions are relative to the start of
+///   the outermost `read_from` or `write_to`.
 ///
-/// Vec field specific attributes:
+/// - `#[plod(if="<expression>")]` the field is an `Option` that is present only if the boolean
+///   `<expression>` is true, it is `None` otherwise. The expression can use the context `ctx` and
+///   earlier fields of the same struct or variant, which are references. On write, an error of
+///   kind `OptionMismatch` is returned if the `Option` doesn't match the expression.
+/// - `#[plod(presence(<type>))]` the field is an `Option` prefixed by a marker of type `<type>`,
+///   which is `1` when it is present and `0` otherwise. Other values can be chosen with
+///   `#[plod(presence(<type>, present=<value>, absent=<value>))]`. An error of kind `InvalidValue`
+///   is returned on read if the marker has another value.
+/// - `#[plod(tag_from=<field>)]` the field is an enum whose tag is not stored before it but in
+///   the earlier `<field>` of the same struct, which must have the enum `tag_type`. The enum is
+///   read and written with `PlodTagged`, on write an error of kind `TagMismatch` is returned if
+///   `<field>` doesn't select the variant, or doesn't match the tag retained by a `keep_tag`
+///   variant. With `#[plod(tag_from=<field>, bytes_from=<size>)]`, the variant must have exactly
+///   the size in bytes given by the earlier `<size>` field, an error of kind `SizeMismatch` is
+///   returned otherwise.
+/// - `#[plod(checksum=<algorithm>)]` the integer field is a checksum of the bytes of other fields,
+///   available algorithms: `crc32`, `crc32c`, `crc16_arc`, `crc16_modbus`, `crc16_ccitt`,
+///   `crc16_xmodem`, `internet` (IPv4 one's complement sum) and `adler32`. By default it covers
+///   every field before it, `#[plod(checksum=<algorithm>, from=<field>, to=<field>)]` chooses the
+///   first and last field covered, the range can contain the checksum itself, which counts as
+///   zeros. It is computed on write, the value of the field is ignored, and verified on read, an
+///   error of kind `BadChecksum` is returned on mismatch unless `verify=false` is given. The field
+///   must be at least as wide as the checksum: 16 bits for `crc16_*` and `internet`, 32 bits for
+///   the others.
+/// - `#[plod(len_of=<field>)]` the integer field is the size in bytes of the later `<field>` of the
+///   same struct or variant, `#[plod(count_of=<field>)]` is its number of items (its `len()`) and
+///   `#[plod(size_of_self)]` is the size in bytes of the whole struct or variant, including its
+///   tag. The value is computed on write, the value of the field is ignored, and checked on read,
+///   an error of kind `SizeMismatch` is returned if it doesn't match. A size that doesn't fit in
+///   the field produces an error of kind `InvalidValue` on write.
+/// - `#[plod(wire=<type>)]` the field is stored as the primitive `<type>`, it is converted with
+///   `From<type>` on read and with `Into<type>` on write, the field type must implement `Clone`.
+///   With `try_from`, `TryFrom<type>` is used on read and an error of kind `InvalidValue` is
+///   returned if it fails. With `try_into`, `TryInto<type>` is used on write and an error of kind
+///   `InvalidConversion` is returned if it fails, `into` is the default.
+/// - `#[plod(with=<module>)]` the field is read, written and sized by functions of `<module>`:
+///   `read<R: PlodRead>(from: &mut R, ctx: &C, pos: usize) -> plod::Result<T>`,
+///   `write<W: PlodWrite>(value: &T, to: &mut W, ctx: &C, pos: usize) -> plod::Result<()>` and
+///   `size(value: &T, pos: usize) -> usize`, where `C` is the context of the struct.
+///   When the type has `#[plod(async)]`, the module also needs `read_async` and `write_async`
+///   async functions with the same parameters, where `R` is a tokio `AsyncRead + Unpin` and `W`
+///   an `AsyncWrite + Unpin`. Types without it only need the synchronous functions.
+/// - `#[plod(read_with=<function>)]`, `#[plod(write_with=<function>)]` and
+///   `#[plod(size_with=<function>)]` replace one of these functions, the other ones are generated
+///   as usual. Without a size function, the field type must implement `Plod`. When the type has
+///   `#[plod(async)]`, an async version of the read and write functions is also needed, its path
+///   is the same with an `_async` suffix, eg: `read_with = codec::decode` also calls
+///   `codec::decode_async`.
+///
+/// Vec field specific attributes, they are also available for `VecDeque`, `BTreeSet`, `HashSet`,
+/// `Box<[T]>` and maps (`BTreeMap` and `HashMap`) whose entries are stored as `(key, value)` tuples:
 /// - `#[plod(size_type(<size_type>))]` defines the type used to store the `Vec` size. This must
 ///   be an integer type. The default is to store the number of items as the _size_.
 /// - `#[plod(bytes_sized)]` means that the size stored is the number of bytes instead of the numer
 ///   of items in the `Vec`
 /// - `#[plod(size_is_next)]` means that the bytes used to store the `Vec` size contains the place
 ///   for the next entry instead of the length of the vector ie: n+1
+/// - `#[plod(rest)]` the `Vec` has no size and contains everything until the end of data, it must
+///   be the last field. Within a `byte_sized` `Vec`, the end of data is the end of this `Vec`.
+///   With `#[plod(borrow)]`, it can also be used on `&'a [u8]` and `&'a str`.
+/// - `#[plod(count_from=<field>)]` the number of items is not stored before the `Vec` but in
+///   `<field>`, an earlier integer field of the same struct or variant. `size_type` is not needed.
+///   On write, an error of kind `SizeMismatch` is returned if `<field>` doesn't match the `Vec`.
+/// - `#[plod(bytes_from=<field>)]` same as `count_from` with a size in bytes.
+/// - `#[plod(terminator=<value>)]` the `Vec` has no size, it ends with an item equal to
+///   `<value>`, which is consumed on read but not stored, and appended on write. Items must
+///   implement `PartialEq`, an error of kind `InvalidValue` is returned on write if an item is
+///   equal to the terminator. `size_type` is not needed. A `String` ending with 0 uses `cstr`
+///   instead.
+///
+/// String field specific attributes:
+/// - By default, a `String` is stored like a `Vec<u8>` with `size_type`, `count_from`, `bytes_from`
+///   or `rest`. It must be valid UTF-8, an error of kind `InvalidUtf8` is returned otherwise.
+/// - `#[plod(cstr)]` (default: `false`): the string is null terminated and has no size. On write,
+///   an error of kind `InvalidValue` is returned if it contains a null byte.
+/// - `#[plod(fixed=<integer>)]`: the string is stored in exactly `<integer>` bytes, padded with
+///   the `fill` byte (`pad` cannot be used with it). Trailing `fill` bytes are removed on read,
+///   an error of kind `TooLong` is returned on write if the string doesn't fit.
+/// - `#[plod(encoding="<encoding>")]` (default: `"utf8"`), available values: `utf8`, `ascii`,
+///   `latin1`, `utf16`, `utf16be`, `utf16le`: text encoding of the string, `utf16` follows the
+///   endianness. Sizes are counted in code units (2 bytes for UTF-16) unless `byte_sized` or
+///   `bytes_from` is used, and a C string ends with a null code unit. Invalid data produces an
+///   error of kind `InvalidEncoding` (`InvalidUtf8` for UTF-8).
+/// - `#[plod(lossy)]` (default: `false`): invalid data is replaced with U+FFFD on read, and
+///   characters that cannot be encoded are replaced with `?` on write, instead of an error.
 ///
 #[proc_macro_derive(Plod, attributes(plod))]
 pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -152,43 +459,125 @@ pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     // get main attributes
     let attributes = unwrap!(Attributes::parse(&input.attrs));
 
-    // generate everything
-    let plod_impl = unwrap!(plod_impl(&input, &attributes));
+    // borrowed types only implement PlodBorrow
+    if attributes.borrow {
+        let name = &input.ident;
+        let lifetime = unwrap!(
+            input.generics.lifetimes().next(),
+            name,
+            "#[plod(borrow)] needs a lifetime parameter"
+        );
+        let lifetime = &lifetime.lifetime;
+        if attributes.repr_c {
+            unwrap!(syn_error(name, "#[plod(repr_c)] cannot be used with #[plod(borrow)]"));
+        }
+        if attributes.is_async {
+            unwrap!(syn_error(name, "#[plod(async)] cannot be used with #[plod(borrow)]"));
+        }
+        let borrow_impl = unwrap!(plod_impl(&input, &attributes, Mode::Borrow));
+        let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+        let ctx_ty = &attributes.context_type;
+        let where_clause = bounded_where_clause(&input.generics, quote! { plod::Plod }, ctx_ty);
+        return proc_macro::TokenStream::from(quote! {
+            #[automatically_derived]
+            impl #impl_generics plod::PlodBorrow<#lifetime> for #name #ty_generics #where_clause {
+                type Context = #ctx_ty;
+                #borrow_impl
+            }
+        });
+    }
+
+    // generate everything, the async impl uses the same field walk
+    let async_impl = match attributes.is_async {
+        true => unwrap!(plod_impl(&input, &attributes, Mode::Async)),
+        false => TokenStream::new(),
+    };
+    let plod_impl = unwrap!(plod_impl(&input, &attributes, Mode::Sync));
 
     // thing for generation
-    let name = input.ident;
-    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
-    let type_params = input.generics.type_params();
+    let name = &input.ident;
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
 
     // define endianness generic
-    let ctx_ty = attributes.context_type;
+    let ctx_ty = &attributes.context_type;
+    let where_clause = bounded_where_clause(&input.generics, quote! { plod::Plod }, ctx_ty);
 
     // Build the output
-    let expanded = quote! {
+    let mut expanded = quote! {
         // The generated impl.
         #[automatically_derived]
-        impl <#(#type_params),*> plod::Plod for #name #ty_generics #where_clause {
+        impl #impl_generics plod::Plod for #name #ty_generics #where_clause {
             type Context= #ctx_ty;
             #plod_impl
         }
     };
 
+    // AsyncPlod is only implemented on demand, fields must implement it too
+    if attributes.is_async {
+        let async_where_clause = bounded_where_clause(&input.generics, quote! { plod::AsyncPlod }, ctx_ty);
+        expanded.extend(quote! {
+            #[automatically_derived]
+            impl #impl_generics plod::AsyncPlod for #name #ty_generics #async_where_clause {
+                #async_impl
+            }
+        });
+    }
+
+    // enums can also be read and written without their tag
+    if let Data::Enum(data) = &input.data {
+        let sync_tagged_impl = unwrap!(tagged_impl(&input, data, &attributes, Mode::Sync));
+        expanded.extend(quote! {
+            #[automatically_derived]
+            impl #impl_generics plod::PlodTagged for #name #ty_generics #where_clause {
+                #sync_tagged_impl
+            }
+        });
+
+        if attributes.is_async {
+            let async_tagged_impl = unwrap!(tagged_impl(&input, data, &attributes, Mode::Async));
+            let async_where_clause = bounded_where_clause(&input.generics, quote! { plod::AsyncPlod }, ctx_ty);
+            expanded.extend(quote! {
+                #[automatically_derived]
+                impl #impl_generics plod::AsyncPlodTagged for #name #ty_generics #async_where_clause {
+                    #async_tagged_impl
+                }
+            });
+        }
+    }
+
     // Hand the output tokens back to the compiler
     proc_macro::TokenStream::from(expanded)
 }
 
+/// Where clause of the generated impl, each type parameter `T` implements `plod_trait` and gets its
+/// context from the type context with `into()`, like any field
+fn bounded_where_clause(generics: &Generics, plod_trait: TokenStream, ctx_ty: &Type) -> WhereClause {
+    let mut where_clause = generics.clone().make_where_clause().clone();
+    for param in generics.type_params() {
+        let ident = &param.ident;
+        where_clause.predicates.push(syn::parse_quote! { #ident: #plod_trait });
+        where_clause.predicates.push(syn::parse_quote! {
+            for<'plod> &'plod <#ident as plod::Plod>::Context: ::core::convert::From<&'plod #ctx_ty>
+        });
+    }
+    where_clause
+}
+
 /// Generate implementation for a given input type (struct or enum)
-fn plod_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream> {
+fn plod_impl(input: &DeriveInput, attributes: &Attributes, mode: Mode) -> Result<TokenStream> {
     let self_name = &input.ident;
 
     let (size_impl, read_impl, write_impl) = match &input.data {
         Data::Struct(data) => {
             // generate for all fields
+            let self_str = self_name.to_string();
             let (size_code, read_code, write_code, field_list) = generate_for_fields(
                 &data.fields,
                 Some(&quote! { self. }),
                 &input.ident,
-                &attributes,
+                attributes,
+                &quote! { .in_type(#self_str) },
+                mode,
             )?;
             (
                 size_code,
@@ -202,7 +591,12 @@ fn plod_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream
                 },
             )
         }
-        Data::Enum(data) => enum_impl(self_name, data, attributes)?,
+        Data::Enum(data) => {
+            if attributes.repr_c {
+                return syn_error(self_name, "#[plod(repr_c)] is only supported on structs");
+            }
+            enum_impl(self_name, data, attributes, mode)?
+        }
         Data::Union(u) => {
             return Err(syn::Error::new(
                 u.union_token.span(),
@@ -211,26 +605,218 @@ fn plod_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream
         }
     };
 
-    Ok(quote! {
-        fn size_at_rest(&self) -> usize {
-            #size_impl
-        }
+    let (size_impl, read_impl, write_impl) =
+        wrap_impl(self_name, attributes, mode, size_impl, read_impl, write_impl)?;
 
-        fn impl_read_from<R: std::io::Read>(from: &mut R, ctx: &Self::Context, mut _pos: usize) -> plod::Result<Self> {
-            #read_impl
+    // C alignment of the type is the largest alignment of its fields
+    let alignment_const = if attributes.repr_c {
+        let mut alignment = quote! { let align = 1; };
+        if let Data::Struct(data) = &input.data {
+            for field in data.fields.iter() {
+                let field_attributes = attributes.extend(&field.attrs)?;
+                if field_attributes.skip {
+                    continue;
+                }
+                let mut field_alignments = vec![c_alignment(&field.ty)?];
+                if let Some(align) = &field_attributes.align {
+                    field_alignments.push(quote! { #align });
+                }
+                for field_alignment in field_alignments {
+                    alignment.extend(quote! {
+                        let align = if #field_alignment > align { #field_alignment } else { align };
+                    });
+                }
+            }
+        }
+        if let Some(align) = &attributes.align {
+            alignment.extend(quote! {
+                let align = if #align > align { #align } else { align };
+            });
         }
+        quote! { const ALIGNMENT: usize = { #alignment align }; }
+    } else if let Some(align) = &attributes.align {
+        quote! { const ALIGNMENT: usize = #align; }
+    } else {
+        TokenStream::new()
+    };
 
-        fn impl_write_to<W: std::io::Write>(&self, to: &mut W, ctx: &Self::Context, mut _pos: usize) -> plod::Result<()> {
-            #write_impl
+    Ok(match mode {
+        Mode::Sync => quote! {
+            #alignment_const
+
+            fn size_at_rest(&self) -> usize {
+                self.size_at_pos(0)
+            }
+
+            fn size_at_pos(&self, pos: usize) -> usize {
+                let mut _pos = pos;
+                #size_impl
+                _pos - pos
+            }
+
+            fn impl_read_from<R: plod::PlodRead>(from: &mut R, ctx: &Self::Context, mut _pos: usize) -> plod::Result<Self> {
+                #read_impl
+            }
+
+            fn impl_write_to<W: plod::PlodWrite>(&self, to: &mut W, ctx: &Self::Context, mut _pos: usize) -> plod::Result<()> {
+                #write_impl
+            }
+        },
+        Mode::Async => quote! {
+            async fn impl_read_from_async<R: plod::__private::AsyncRead + Unpin>(from: &mut R, ctx: &Self::Context, mut _pos: usize) -> plod::Result<Self> {
+                #read_impl
+            }
+
+            async fn impl_write_to_async<W: plod::__private::AsyncWrite + Unpin>(&self, to: &mut W, ctx: &Self::Context, mut _pos: usize) -> plod::Result<()> {
+                #write_impl
+            }
+        },
+        Mode::Borrow => {
+            // checked by derive
+            let lifetime = &input.generics.lifetimes().next().unwrap().lifetime;
+            quote! {
+                fn impl_parse(input: &#lifetime [u8], ctx: &Self::Context, mut _pos: usize) -> plod::Result<(Self, &#lifetime [u8])> {
+                    let mut input = input;
+                    let from = &mut input;
+                    let value: plod::Result<Self> = { #read_impl };
+                    Ok((value?, input))
+                }
+            }
         }
     })
 }
 
+/// Wrap the implementation of a type with the code that applies to the whole type, alignment and
+/// byte order
+fn wrap_impl(
+    self_name: &Ident,
+    attributes: &Attributes,
+    mode: Mode,
+    size_impl: TokenStream,
+    read_impl: TokenStream,
+    write_impl: TokenStream,
+) -> Result<(TokenStream, TokenStream, TokenStream)> {
+    // an aligned type is padded at its start and at its end
+    if attributes.pad.is_some() {
+        return syn_error(self_name, "#[plod(pad=<value>)] is only supported on fields");
+    }
+    let alignment = if attributes.repr_c {
+        Some(quote! { <Self as plod::Plod>::ALIGNMENT })
+    } else {
+        attributes.align.as_ref().map(|align| quote! { #align })
+    };
+    let (size_impl, read_impl, write_impl) = if let Some(alignment) = alignment {
+        let self_str = self_name.to_string();
+        let mut size_padding = TokenStream::new();
+        let mut read_padding = TokenStream::new();
+        let mut write_padding = TokenStream::new();
+        generate_alignment(
+            alignment,
+            attributes,
+            &mut size_padding,
+            &mut read_padding,
+            &mut write_padding,
+            &quote! { .in_type(#self_str) },
+            mode,
+        );
+        (
+            quote! {
+                #size_padding
+                #size_impl
+                #size_padding
+            },
+            quote! {
+                #read_padding
+                let value: plod::Result<Self> = { #read_impl };
+                let value = value?;
+                #read_padding
+                Ok(value)
+            },
+            quote! {
+                #write_padding
+                let result: plod::Result<()> = { #write_impl };
+                result?;
+                #write_padding
+                Ok(())
+            },
+        )
+    } else {
+        (size_impl, read_impl, write_impl)
+    };
+
+    // the byte order of a dynamic type comes from its context, a magic value can override it
+    let (size_impl, read_impl, write_impl) = if attributes.endianness == Endianness::Dynamic {
+        (
+            quote! {
+                // sizes don't depend on the byte order
+                #[allow(unused_variables)]
+                let byte_order = plod::ByteOrder::NATIVE;
+                #size_impl
+            },
+            quote! {
+                #[allow(unused_mut)]
+                let mut byte_order = plod::ByteOrder::from(ctx);
+                #read_impl
+            },
+            quote! {
+                let byte_order = plod::ByteOrder::from(ctx);
+                #write_impl
+            },
+        )
+    } else {
+        (size_impl, read_impl, write_impl)
+    };
+    Ok((size_impl, read_impl, write_impl))
+}
+
+/// Generate `PlodTagged` implementation for an enum, its variants are read and written without their tag
+fn tagged_impl(input: &DeriveInput, data: &DataEnum, attributes: &Attributes, mode: Mode) -> Result<TokenStream> {
+    let self_name = &input.ident;
+    let mut attributes = attributes.clone();
+    attributes.untagged = true;
+    let (size_impl, read_impl, write_impl) = enum_impl(self_name, data, &attributes, mode)?;
+    let (size_impl, read_impl, write_impl) =
+        wrap_impl(self_name, &attributes, mode, size_impl, read_impl, write_impl)?;
+    // checked by enum_impl
+    let tag_type = attributes.tag_type.as_ref().unwrap();
+
+    Ok(match mode {
+        Mode::Sync => quote! {
+            type Tag = #tag_type;
+
+            fn untagged_size_at_pos(&self, pos: usize) -> usize {
+                let mut _pos = pos;
+                #size_impl
+                _pos - pos
+            }
+
+            fn impl_read_untagged<R: plod::PlodRead>(untagged_tag: #tag_type, from: &mut R, ctx: &Self::Context, mut _pos: usize) -> plod::Result<Self> {
+                #read_impl
+            }
+
+            fn impl_write_untagged<W: plod::PlodWrite>(&self, untagged_tag: #tag_type, to: &mut W, ctx: &Self::Context, mut _pos: usize) -> plod::Result<()> {
+                #write_impl
+            }
+        },
+        Mode::Async => quote! {
+            async fn impl_read_untagged_async<R: plod::__private::AsyncRead + Unpin>(untagged_tag: #tag_type, from: &mut R, ctx: &Self::Context, mut _pos: usize) -> plod::Result<Self> {
+                #read_impl
+            }
+
+            async fn impl_write_untagged_async<W: plod::__private::AsyncWrite + Unpin>(&self, untagged_tag: #tag_type, to: &mut W, ctx: &Self::Context, mut _pos: usize) -> plod::Result<()> {
+                #write_impl
+            }
+        },
+        Mode::Borrow => unreachable!("borrowed enums don't implement PlodTagged"),
+    })
+}
+
 /// Generate code for all variants of an enum
 fn enum_impl(
     self_name: &Ident,
     data: &DataEnum,
     attributes: &Attributes,
+    mode: Mode,
 ) -> Result<(TokenStream, TokenStream, TokenStream)> {
     let mut size_impl = TokenStream::new();
     let mut read_impl = TokenStream::new();
@@ -252,32 +838,60 @@ fn enum_impl(
         );
     }
     let tag_size = primitive_size(tag_type);
-    let (from_method, to_method) = primitive_function(attributes.endianness);
+    let self_str = self_name.to_string();
+
+    // without tag, the variant written must be the one that the tag selects on read
+    let mut selector = TokenStream::new();
+    if attributes.untagged {
+        let mut default_index = quote! { usize::MAX };
+        for (i, variant) in data.variants.iter().enumerate() {
+            let variant_attributes = attributes.extend(&variant.attrs)?;
+            match &variant_attributes.tag {
+                _ if variant_attributes.skip => {}
+                Some(value) => selector.extend(quote! { #value => #i, }),
+                None => default_index = quote! { #i },
+            }
+        }
+        selector = quote! {
+            match untagged_tag {
+                #selector
+                _ => #default_index,
+            }
+        };
+    }
 
     // iterate over variants
     let mut default_done = false;
-    for variant in data.variants.iter() {
+    for (index, variant) in data.variants.iter().enumerate() {
         let ident = &variant.ident;
+        let ident_str = ident.to_string();
+        let error_path = quote! { .in_variant(#ident_str).in_type(#self_str) };
 
         // check variant attributes
         let variant_attributes = attributes.extend(&variant.attrs)?;
+        if variant_attributes.align.is_some() || variant_attributes.pad.is_some() {
+            return syn_error(ident, "Padding is only supported on fields and types");
+        }
         let tag_value = &variant_attributes.tag;
+        if variant_attributes.other {
+            if let Some(tag) = tag_value {
+                return syn_error(tag, "#[plod(other)] catches all tags, it cannot have a tag");
+            }
+        }
 
         // handle skipped values, no size code, no read code, error on write
         if variant_attributes.skip {
-            let error_token = quote! { #self_name::#ident };
-            let error_str = error_token.to_string();
             let fields_token = if let Fields::Unit = variant.fields {
                 TokenStream::new()
             } else {
                 quote! { (..) }
             };
             size_impl.extend(quote! {
-                #self_name::#ident #fields_token => 0,
+                #self_name::#ident #fields_token => {}
             });
             write_impl.extend(quote! {
                 #self_name::#ident #fields_token => {
-                    return Err(std::io::Error::other(format!("Variant {} cannot be written  because it is plod(skipped)", #error_str)));
+                    return Err(plod::Error::new(plod::ErrorKind::SkippedVariant { variant: #ident_str }).at(_pos).in_type(#self_str));
                 }
             });
             continue;
@@ -293,7 +907,7 @@ fn enum_impl(
 
         // generate for all fields
         let (size_code, read_code, write_code, field_list) =
-            generate_for_fields(&variant.fields, None, &variant.ident, &variant_attributes)?;
+            generate_for_fields(&variant.fields, None, &variant.ident, &variant_attributes, &error_path, mode)?;
 
         // code for reading variant
         match &tag_value {
@@ -315,7 +929,36 @@ fn enum_impl(
         }
 
         // code for writing variant
-        let add_tag = if variant_attributes.keep_tag {
+        let map_tag_error = map_error(&error_path);
+        let write_tag = mode.write_all(quote! { &buffer });
+        let add_tag = if attributes.untagged {
+            // a retained tag must also be the tag given
+            let kept_tag = match variant.fields.iter().next() {
+                Some(field) if variant_attributes.keep_tag => {
+                    let field_ident = match &field.ident {
+                        Some(ident) => ident.clone(),
+                        None => Ident::new("field_0", field.span()),
+                    };
+                    let diff = match &variant_attributes.keep_diff {
+                        Some(diff) => quote! { + #diff },
+                        None => TokenStream::new(),
+                    };
+                    quote! { || (*#field_ident #diff) as #tag_type != untagged_tag }
+                }
+                _ => TokenStream::new(),
+            };
+            quote! {
+                #[allow(clippy::match_single_binding)]
+                let selected: usize = #selector;
+                if selected != #index #kept_tag {
+                    return Err(plod::Error::new(plod::ErrorKind::TagMismatch {
+                        type_name: #self_str,
+                        variant: #ident_str,
+                        value: plod::__private::ToString::to_string(&untagged_tag),
+                    }).at(_pos).in_type(#self_str));
+                }
+            }
+        } else if variant_attributes.keep_tag {
             TokenStream::new()
         } else {
             let tag_pattern = match &variant_attributes.tag {
@@ -330,9 +973,11 @@ fn enum_impl(
                     return syn_error(tag_type, "#[plod(keep_tag)] is mandatory with tag patterns")
                 }
             };
+            let tag_bytes = to_bytes(attributes.endianness, quote! { (#tag_value as #tag_type) });
             quote! {
-                let buffer: [u8; #tag_size] = (#tag_value as #tag_type).#to_method();
-                to.write_all(&buffer)?;
+                let buffer: [u8; #tag_size] = #tag_bytes;
+                #write_tag #map_tag_error;
+                _pos += #tag_size;
             }
         };
         write_impl.extend(quote! {
@@ -344,7 +989,9 @@ fn enum_impl(
 
         // code for getting size
         size_impl.extend(quote! {
-            #self_name::#ident #field_list => #size_code,
+            #self_name::#ident #field_list => {
+                #size_code
+            }
         });
     }
     // Finalize size_impl
@@ -354,11 +1001,21 @@ fn enum_impl(
         }
     };
     // finalize read_impl
-    let read_tag = quote! {
-        let mut buffer: [u8; #tag_size] = [0; #tag_size];
-        from.read_exact(&mut buffer)?;
-        let discriminant = #tag_type::#from_method(buffer);
-        _pos += #tag_size;
+    let map_tag_error = map_error(&quote! { .in_type(#self_str) });
+    let read_buffer = mode.read_exact(quote! { &mut buffer });
+    let discriminant = from_bytes(attributes.endianness, tag_type, quote! { buffer });
+    let (read_tag, tag_pos) = if attributes.untagged {
+        (quote! { let discriminant = untagged_tag; }, quote! { _pos })
+    } else {
+        (
+            quote! {
+                let mut buffer: [u8; #tag_size] = [0; #tag_size];
+                #read_buffer #map_tag_error;
+                let discriminant = #discriminant;
+                _pos += #tag_size;
+            },
+            quote! { _pos - #tag_size },
+        )
     };
     if default_done {
         read_impl = quote! {
@@ -372,7 +1029,10 @@ fn enum_impl(
             #read_tag
             match discriminant {
                 #read_impl
-                _ => return Err(std::io::Error::other(format!("Tag value {} not found", discriminant))),
+                _ => return Err(plod::Error::new(plod::ErrorKind::UnknownTag {
+                    type_name: #self_str,
+                    value: plod::__private::ToString::to_string(&discriminant),
+                }).at(#tag_pos).in_type(#self_str)),
             }
         };
     }
@@ -392,6 +1052,8 @@ fn generate_for_fields(
     field_prefix: Option<&TokenStream>,
     ident: &Ident,
     attributes: &Attributes,
+    error_path: &TokenStream,
+    mode: Mode,
 ) -> Result<(TokenStream, TokenStream, TokenStream, TokenStream)> {
     let mut size_code = TokenStream::new();
     let mut read_code = TokenStream::new();
@@ -399,39 +1061,92 @@ fn generate_for_fields(
     let mut field_list = TokenStream::new();
     let mut context_val = quote! { ctx };
     let mut prefixed_context_val = quote! { ctx };
+    let mut bit_group = None;
+    // size of the tag, it is read by the enum and written by the variant
+    let mut start = quote! { _pos };
+    if !attributes.keep_tag && !attributes.untagged {
+        if let Some(ty) = &attributes.tag_type {
+            let ty_size = primitive_size(ty);
+            size_code.extend(quote! { _pos += #ty_size; });
+            start = quote! { _pos - #ty_size };
+        }
+    }
     if let Some((ty, value)) = &attributes.magic {
-        let (from_method, to_method) = primitive_function(attributes.endianness);
         if !primitive_type(ty) {
             return syn_error(ty, "magic only works with primitive types");
         }
         let ty_size = primitive_size(ty);
+        let map_magic_error = map_error(error_path);
+        let read_buffer = mode.read_exact(quote! { &mut buffer });
+        let write_buffer = mode.write_all(quote! { &buffer });
 
         // size code
         size_code.extend(quote! {
-            #ty_size +
+            _pos += #ty_size;
         });
+        let magic_value = from_bytes(attributes.endianness, ty, quote! { buffer });
+        let magic_bytes = to_bytes(attributes.endianness, quote! { (#value as #ty) });
+        // with a dynamic endianness, the magic value can be found in the other byte order
+        let swap_byte_order = if attributes.endianness == Endianness::Dynamic {
+            let byte_order_context = match &attributes.context_type {
+                Type::Path(type_path) => type_path.path.segments.last().is_some_and(|s| s.ident == "ByteOrder"),
+                _ => false,
+            };
+            if !byte_order_context {
+                return syn_error(
+                    ty,
+                    "A magic value with a dynamic endianness needs #[plod(context = plod::ByteOrder)]",
+                );
+            }
+            // next fields get the byte order found as their context
+            context_val = quote! { (&byte_order) };
+            prefixed_context_val = quote! { (&byte_order) };
+            quote! {
+                if magic != #value {
+                    byte_order = byte_order.swapped();
+                    let swapped = #magic_value;
+                    if swapped != #value {
+                        byte_order = byte_order.swapped();
+                    }
+                }
+                let magic = #magic_value;
+            }
+        } else {
+            TokenStream::new()
+        };
         read_code.extend(quote! {
             let mut buffer: [u8; #ty_size] = [0; #ty_size];
-            from.read_exact(&mut buffer)?;
-            let magic = #ty::#from_method(buffer);
+            #read_buffer #map_magic_error;
+            let magic = #magic_value;
+            #swap_byte_order
             if magic != #value {
-                return Err(std::io::Error::other(format!("Magic value {} expected, found {}", #value, magic)));
+                return Err(plod::Error::new(plod::ErrorKind::BadMagic {
+                    expected: plod::__private::ToString::to_string(&(#value as #ty)),
+                    found: plod::__private::ToString::to_string(&magic),
+                }).at(_pos) #error_path);
             }
             _pos += #ty_size;
         });
         write_code.extend(quote! {
-            let buffer: [u8; #ty_size] = (#value as #ty).#to_method();
-            to.write_all(&buffer)?;
+            let buffer: [u8; #ty_size] = #magic_bytes;
+            #write_buffer #map_magic_error;
             _pos += #ty_size;
         });
     }
+    let mut checksums = Checksums::new(fields, attributes, error_path)?;
+    let mut sizes = SizeFields::new(fields, attributes)?;
     match fields {
         Fields::Named(fields) => {
             let mut i = 0;
+            // fields that can be referenced by the next ones, with their reference
+            let mut previous_fields = Vec::new();
             for field in fields.named.iter() {
-                let field_attributes = attributes.extend(&field.attrs)?;
+                let field_attributes = field_attributes(attributes, field, i, fields.named.len())?;
+                checksums.begin(i, &mut read_code, &mut write_code);
                 // all named fields have an ident
                 let field_ident = field.ident.as_ref().unwrap();
+                let field_str = field_ident.to_string();
+                let field_error_path = quote! { .in_field(#field_str) #error_path };
                 let (prefixed_field_ref, prefixed_field_dotted) = match field_prefix {
                     None => (quote! { #field_ident }, quote! { #field_ident .}),
                     Some(prefix) => (
@@ -439,20 +1154,121 @@ fn generate_for_fields(
                         quote! {  #prefix #field_ident . },
                     ),
                 };
-                generate_for_item(
-                    &field_ident,
-                    &field.ty,
-                    &prefixed_field_ref,
-                    &prefixed_field_dotted,
-                    // TODO field_attributes keep tag ?
-                    i == 0 && attributes.keep_tag,
-                    &field_attributes,
-                    &mut size_code,
-                    &mut read_code,
-                    &mut write_code,
-                    &context_val,
-                    &prefixed_context_val,
-                )?;
+                // a size field writes the value computed, later fields see it too
+                let prefixed_field_ref = sizes.field_ref(i).unwrap_or(prefixed_field_ref);
+                if let Some(size_field) = &field_attributes.size_from {
+                    let size_field_ref = match previous_fields.iter().find(|(ident, _)| ident == size_field) {
+                        Some((_, field_ref)) => field_ref,
+                        None => return syn_error(size_field, "The size must be an earlier field of this struct"),
+                    };
+                    read_code.extend(quote! {
+                        let size_from = #size_field;
+                    });
+                    write_code.extend(quote! {
+                        let size_from = *#size_field_ref;
+                    });
+                }
+                if let Some(tag_field) = &field_attributes.tag_from {
+                    let tag_field_ref = match previous_fields.iter().find(|(ident, _)| ident == tag_field) {
+                        Some((_, field_ref)) => field_ref,
+                        None => return syn_error(tag_field, "The tag must be an earlier field of this struct"),
+                    };
+                    read_code.extend(quote! {
+                        let tag_from = #tag_field;
+                    });
+                    write_code.extend(quote! {
+                        let tag_from = *#tag_field_ref;
+                    });
+                }
+                if let Some(condition) = &field_attributes.condition {
+                    // earlier fields are available as references
+                    let read_bindings = previous_fields.iter().map(|(ident, _)| {
+                        quote! { #[allow(unused_variables)] let #ident = &#ident; }
+                    });
+                    let write_bindings = previous_fields.iter().map(|(ident, field_ref)| {
+                        quote! { #[allow(unused_variables)] let #ident = #field_ref; }
+                    });
+                    read_code.extend(quote! {
+                        let condition: bool = { #(#read_bindings)* #condition };
+                    });
+                    write_code.extend(quote! {
+                        let condition: bool = { #(#write_bindings)* #condition };
+                    });
+                }
+                previous_fields.push((field_ident.clone(), prefixed_field_ref.clone()));
+                if field_attributes.bits.is_some() {
+                    generate_for_bitfield(
+                        &mut bit_group,
+                        field_ident,
+                        &field.ty,
+                        &prefixed_field_ref,
+                        &field_attributes,
+                        attributes.repr_c,
+                        &mut size_code,
+                        &mut read_code,
+                        &mut write_code,
+                        &field_error_path,
+                        mode,
+                    )?;
+                } else {
+                    if !field_attributes.skip {
+                        check_bit_group(&bit_group)?;
+                        generate_item_padding(
+                            &field_attributes,
+                            attributes.repr_c.then_some(&field.ty),
+                            &mut size_code,
+                            &mut read_code,
+                            &mut write_code,
+                            &field_error_path,
+                            mode,
+                        )?;
+                    }
+                    sizes.begin(i, &mut read_code);
+                    let mut item_size_code = TokenStream::new();
+                    if let Some(span) = checksums.field(i) {
+                        generate_for_checksum(
+                            span,
+                            field_ident,
+                            &field.ty,
+                            &prefixed_field_ref,
+                            &prefixed_field_dotted,
+                            &field_attributes,
+                            &mut item_size_code,
+                            &mut read_code,
+                            &mut write_code,
+                            &field_error_path,
+                            mode,
+                        )?;
+                    } else {
+                        generate_for_item(
+                            field_ident,
+                            &field.ty,
+                            &prefixed_field_ref,
+                            &prefixed_field_dotted,
+                            // TODO field_attributes keep tag ?
+                            i == 0 && attributes.keep_tag,
+                            &field_attributes,
+                            &mut item_size_code,
+                            &mut read_code,
+                            &mut write_code,
+                            &context_val,
+                            &prefixed_context_val,
+                            &field_error_path,
+                            mode,
+                        )?;
+                    }
+                    sizes.end(
+                        i,
+                        field_ident,
+                        &prefixed_field_ref,
+                        &size_code,
+                        &item_size_code,
+                        &mut read_code,
+                        &field_error_path,
+                    );
+                    size_code.extend(item_size_code);
+                }
+                checksums.end(i, field_ident, &bit_group, &mut read_code, &mut write_code, error_path, mode)?;
                 if field_attributes.is_context {
                     context_val = quote! { (&#field_ident) };
                     prefixed_context_val = prefixed_field_ref;
@@ -465,9 +1281,18 @@ fn generate_for_fields(
             field_list = quote! { { #field_list } };
         }
         Fields::Unnamed(fields) => {
+            let single = fields.unnamed.len() == 1;
             for (i, field) in fields.unnamed.iter().enumerate() {
-                let field_attributes = attributes.extend(&field.attrs)?;
+                let field_attributes = field_attributes(attributes, field, i, fields.unnamed.len())?;
+                checksums.begin(i, &mut read_code, &mut write_code);
                 let field_ident = Ident::new(&format!("field_{}", i), field.span());
+                // a lone tuple field is not worth being named in the error path
+                let field_error_path = if single {
+                    error_path.clone()
+                } else {
+                    let field_str = i.to_string();
+                    quote! { .in_field(#field_str) #error_path }
+                };
                 let (prefixed_field_ref, prefixed_field_dotted) = match field_prefix {
                     None => (quote! { #field_ident }, quote! { #field_ident .}),
                     Some(prefix) => {
@@ -475,19 +1300,78 @@ fn generate_for_fields(
                         (quote! {  ( & #prefix #i ) }, quote! {  #prefix #i . })
                     }
                 };
-                generate_for_item(
-                    &field_ident,
-                    &field.ty,
-                    &prefixed_field_ref,
-                    &prefixed_field_dotted,
-                    i == 0 && attributes.keep_tag,
-                    &field_attributes,
-                    &mut size_code,
-                    &mut read_code,
-                    &mut write_code,
-                    &context_val,
-                    &prefixed_context_val,
-                )?;
+                let prefixed_field_ref = sizes.field_ref(i).unwrap_or(prefixed_field_ref);
+                if let Some(size_field) = &field_attributes.size_from {
+                    return syn_error(size_field, "The size can only be taken from a named field");
+                }
+                if let Some(condition) = &field_attributes.condition {
+                    return syn_error(condition, "#[plod(if)] can only be used on a named field");
+                }
+                if let Some(tag_field) = &field_attributes.tag_from {
+                    return syn_error(tag_field, "The tag can only be taken from a named field");
+                }
+                if field_attributes.bits.is_some() {
+                    generate_for_bitfield(
+                        &mut bit_group,
+                        &field_ident,
+                        &field.ty,
+                        &prefixed_field_ref,
+                        &field_attributes,
+                        attributes.repr_c,
+                        &mut size_code,
+                        &mut read_code,
+                        &mut write_code,
+                        &field_error_path,
+                        mode,
+                    )?;
+                } else {
+                    if !field_attributes.skip {
+                        check_bit_group(&bit_group)?;
+                        generate_item_padding(
+                            &field_attributes,
+                            attributes.repr_c.then_some(&field.ty),
+                            &mut size_code,
+                            &mut read_code,
+                            &mut write_code,
+                            &field_error_path,
+                            mode,
+                        )?;
+                    }
+                    let mut item_size_code = TokenStream::new();
+                    if let Some(span) = checksums.field(i) {
+                        generate_for_checksum(
+                            span,
+                            &field_ident,
+                            &field.ty,
+                            &prefixed_field_ref,
+                            &prefixed_field_dotted,
+                            &field_attributes,
+                            &mut item_size_code,
+                            &mut read_code,
+                            &mut write_code,
+                            &field_error_path,
+                            mode,
+                        )?;
+                    } else {
+                        generate_for_item(
+                            &field_ident,
+                            &field.ty,
+                            &prefixed_field_ref,
+                            &prefixed_field_dotted,
+                            i == 0 && attributes.keep_tag,
+                            &field_attributes,
+                            &mut item_size_code,
+                            &mut read_code,
+                            &mut write_code,
+                            &context_val,
+                            &prefixed_context_val,
+                            &field_error_path,
+                            mode,
+                        )?;
+                    }
+                    size_code.extend(item_size_code);
+                }
+                checksums.end(i, &field_ident, &bit_group, &mut read_code, &mut write_code, error_path, mode)?;
                 if field_attributes.is_context {
                     context_val = quote! { (&#field_ident) };
                     prefixed_context_val = quote! { #prefixed_field_ref };
@@ -505,55 +1389,702 @@ fn generate_for_fields(
             }
         }
     };
-    // final part of size for the tag
-    if attributes.keep_tag {
-        size_code.extend(quote! { 0 });
-    } else {
-        match &attributes.tag_type {
-            None => size_code.extend(quote! { 0 }),
-            Some(ty) => {
-                let ty_size = primitive_size(ty);
-                size_code.extend(quote! { #ty_size });
-            }
-        }
-    }
+    check_bit_group(&bit_group)?;
+    sizes.finish(start, &size_code, &mut read_code, &mut write_code, error_path);
     Ok((size_code, read_code, write_code, field_list))
 }
 
-/// Generate code for a single item of a variant or a struct
-fn generate_for_item(
-    field_ident: &Ident,
-    field_type: &Type,
-    prefixed_field_ref: &TokenStream,
-    prefixed_field_dotted: &TokenStream,
-    is_tag: bool,
+/// A checksum field and the fields it covers
+struct ChecksumSpan {
+    /// checksum field
+    field_ident: Ident,
+    ty: Ident,
+    endianness: Endianness,
+    /// `plod::__private::Checksum` variant
+    algorithm: Ident,
+    verify: bool,
+    /// error path of the checksum field
+    error_path: TokenStream,
+    /// index of the checksum field
+    index: usize,
+    /// index of the first field covered
+    start: usize,
+    /// index of the last field covered
+    end: usize,
+}
+
+impl ChecksumSpan {
+    /// An embedded checksum is inside the fields it covers, its bytes count as zeros
+    fn embedded(&self) -> bool {
+        self.index <= self.end
+    }
+
+    /// Variable that contains the computed checksum
+    fn computed(&self) -> Ident {
+        Ident::new(&format!("checksum_{}", self.field_ident), self.field_ident.span())
+    }
+
+    /// Expression that computes the checksum of `checksum_data`
+    fn compute(&self) -> TokenStream {
+        let ty = &self.ty;
+        let algorithm = &self.algorithm;
+        let zeroed = if self.embedded() {
+            let size = primitive_size(ty);
+            quote! { Some(checksum_offset..checksum_offset + #size) }
+        } else {
+            quote! { None }
+        };
+        quote! {
+            <#ty as plod::__private::ChecksumField>::from_checksum(
+                plod::__private::Checksum::#algorithm.compute(&checksum_data, #zeroed)
+            )
+        }
+    }
+
+    /// Code that checks the checksum field against the computed value, `pos` is its position
+    fn check(&self, pos: TokenStream) -> TokenStream {
+        let algorithm = &self.algorithm;
+        let error_path = &self.error_path;
+        let field_ident = &self.field_ident;
+        let computed = self.computed();
+        quote! {
+            plod::__private::Checksum::#algorithm.check(&#field_ident, &#computed)
+                .map_err(|e| e.at(#pos) #error_path)?;
+        }
+    }
+}
+
+/// Checksums of a struct or a variant, the code of the fields they cover is generated apart and
+/// wrapped after the last one
+struct Checksums {
+    spans: Vec<ChecksumSpan>,
+    /// outer read and write code, and variables defined by the fields covered so far
+    current: Option<(TokenStream, TokenStream, Vec<Ident>)>,
+}
+
+impl Checksums {
+    /// Find checksum fields and the fields they cover
+    fn new(fields: &Fields, attributes: &Attributes, error_path: &TokenStream) -> Result<Self> {
+        let mut spans: Vec<ChecksumSpan> = Vec::new();
+        let index_of = |name: &Ident| -> Result<usize> {
+            match fields.iter().position(|field| field.ident.as_ref() == Some(name)) {
+                Some(index) => Ok(index),
+                None => syn_error(name, "A checksum can only cover named fields of this struct"),
+            }
+        };
+        for (index, field) in fields.iter().enumerate() {
+            let field_attributes = attributes.extend(&field.attrs)?;
+            let algorithm = match &field_attributes.checksum {
+                Some(algorithm) => algorithm,
+                None => match field_attributes.checksum_from.as_ref().or(field_attributes.checksum_to.as_ref()) {
+                    Some(name) => return syn_error(name, "#[plod(from=..)] and #[plod(to=..)] are only used with checksum"),
+                    None => continue,
+                },
+            };
+            if field_attributes.bits.is_some() || field_attributes.skip {
+                return syn_error(field, "A checksum cannot be a bitfield or be skipped");
+            }
+            let ty = match &field.ty {
+                Type::Path(type_path) if type_path.path.get_ident().is_some_and(primitive_type) => {
+                    type_path.path.get_ident().unwrap().clone()
+                }
+                _ => return syn_error(&field.ty, "A checksum must be an integer"),
+            };
+            let width = checksum_size(algorithm);
+            if primitive_size(&ty).base10_parse::<usize>()? < width {
+                return syn_error(
+                    &field.ty,
+                    &format!("A {} checksum needs a field of at least {} bytes", algorithm, width),
+                );
+            }
+            let start = match &field_attributes.checksum_from {
+                Some(name) => index_of(name)?,
+                None => 0,
+            };
+            let end = match &field_attributes.checksum_to {
+                Some(name) => index_of(name)?,
+                None if index == 0 => return syn_error(field, "A checksum needs earlier fields to cover"),
+                None => index - 1,
+            };
+            if start > end || index < start {
+                return syn_error(field, "A checksum must come after the first field it covers");
+            }
+            // checksum_crc16_ccitt -> Crc16Ccitt
+            let algorithm_name: String = algorithm
+                .to_string()
+                .split('_')
+                .map(|word| word[..1].to_uppercase() + &word[1..])
+                .collect();
+            let (field_ident, field_str) = match &field.ident {
+                Some(ident) => (ident.clone(), ident.to_string()),
+                None => (Ident::new(&format!("field_{}", index), field.span()), index.to_string()),
+            };
+            let span = ChecksumSpan {
+                error_path: quote! { .in_field(#field_str) #error_path },
+                field_ident,
+                ty,
+                endianness: field_attributes.endianness,
+                algorithm: Ident::new(&algorithm_name, algorithm.span()),
+                verify: field_attributes.verify,
+                index,
+                start,
+                end,
+            };
+            if spans.iter().any(|other| span.start <= other.end && other.start <= span.end) {
+                return syn_error(field, "Fields covered by checksums cannot overlap");
+            }
+            spans.push(span);
+        }
+        Ok(Checksums { spans, current: None })
+    }
+
+    /// Checksum stored in field `index`
+    fn field(&self, index: usize) -> Option<&ChecksumSpan> {
+        self.spans.iter().find(|span| span.index == index)
+    }
+
+    /// Called before the code of field `index`
+    fn begin(&mut self, index: usize, read_code: &mut TokenStream, write_code: &mut TokenStream) {
+        if self.spans.iter().any(|span| span.start == index) {
+            self.current = Some((std::mem::take(read_code), std::mem::take(write_code), Vec::new()));
+        }
+    }
+
+    /// Called after the code of field `index`, `field_ident` is the variable it defines on read
+    fn end(
+        &mut self,
+        index: usize,
+        field_ident: &Ident,
+        bit_group: &Option<BitGroup>,
+        read_code: &mut TokenStream,
+        write_code: &mut TokenStream,
+        error_path: &TokenStream,
+        mode: Mode,
+    ) -> Result<()> {
+        match &mut self.current {
+            Some((_, _, fields)) => fields.push(field_ident.clone()),
+            None => return Ok(()),
+        }
+        let span = match self.spans.iter().find(|span| span.end == index) {
+            Some(span) => span,
+            None => return Ok(()),
+        };
+        check_bit_group(bit_group)?;
+        // checked above
+        let (outer_read, outer_write, fields) = self.current.take().unwrap();
+        let computed = span.computed();
+        let compute = span.compute();
+        let (offset, checksum_start) = if span.embedded() {
+            (quote! { checksum_offset }, quote! { let checksum_start = _pos; })
+        } else {
+            (TokenStream::new(), TokenStream::new())
+        };
+
+        // read: covered bytes are recorded, or taken from the input when it is borrowed
+        let span_read = std::mem::replace(read_code, outer_read);
+        if !span.verify {
+            read_code.extend(span_read);
+        } else {
+            if mode == Mode::Borrow {
+                read_code.extend(quote! {
+                    #checksum_start
+                    let checksum_input: &[u8] = *from;
+                    #span_read
+                    let checksum_data = &checksum_input[..checksum_input.len() - from.len()];
+                });
+            } else {
+                read_code.extend(quote! {
+                    #checksum_start
+                    let mut checksum_data = plod::__private::Vec::new();
+                    let (#(#fields,)* #offset) = {
+                        let mut from = plod::__private::Recorder::new(&mut *from, &mut checksum_data);
+                        let from = &mut from;
+                        #span_read
+                        (#(#fields,)* #offset)
+                    };
+                });
+            }
+            read_code.extend(quote! {
+                let #computed = #compute;
+            });
+            if span.embedded() {
+                read_code.extend(span.check(quote! { checksum_start + checksum_offset }));
+            }
+        }
+
+        // write: covered bytes are written to a buffer first
+        let span_write = std::mem::replace(write_code, outer_write);
+        let write_buffer = mode.write_all(quote! { &checksum_data });
+        write_code.extend(quote! {
+            let checksum_start = _pos;
+            let mut checksum_data: plod::__private::Vec<u8> = plod::__private::Vec::new();
+        });
+        if span.embedded() {
+            let size = primitive_size(&span.ty);
+            let bytes = to_bytes(span.endianness, quote! { #computed });
+            write_code.extend(quote! {
+                let checksum_offset = {
+                    let to = &mut checksum_data;
+                    #span_write
+                    checksum_offset
+                };
+                let #computed = #compute;
+                checksum_data[checksum_offset..checksum_offset + #size].copy_from_slice(&#bytes);
+            });
+        } else {
+            write_code.extend(quote! {
+                {
+                    let to = &mut checksum_data;
+                    #span_write
+                }
+                let #computed = #compute;
+            });
+        }
+        write_code.extend(quote! {
+            #write_buffer.map_err(|e| plod::Error::from(e).at(checksum_start) #error_path)?;
+        });
+        Ok(())
+    }
+}
+
+/// What a size field measures
+enum Measured {
+    /// size in bytes of a later field, with `len_of`
+    Len(usize),
+    /// number of items of a later field, with `count_of`
+    Count(usize),
+    /// size in bytes of the struct or variant, with `size_of_self`
+    Whole,
+}
+
+/// A field whose value is computed on write and checked on read
+struct SizeOf {
+    /// size field
+    field_ident: Ident,
+    field_str: String,
+    ty: Ident,
+    /// index of the size field
+    index: usize,
+    measured: Measured,
+    /// code that computes the value written, once the field measured has been generated
+    compute: Option<TokenStream>,
+}
+
+impl SizeOf {
+    /// Variable that contains the value written
+    fn computed(&self) -> Ident {
+        Ident::new(&format!("computed_{}", self.field_ident), self.field_ident.span())
+    }
+
+    /// Code that checks the size field against `size` on read, `pos` is the position of the error
+    fn check(&self, size: TokenStream, pos: TokenStream, error_path: &TokenStream) -> TokenStream {
+        let field_ident = &self.field_ident;
+        let field_str = &self.field_str;
+        let map_size_error = map_error(error_path);
+        quote! {
+            let expected = plod::__private::SizeField::to_size(#field_ident) #map_size_error;
+            let found: usize = #size;
+            if found != expected {
+                return Err(plod::Error::new(plod::ErrorKind::SizeMismatch {
+                    size_field: #field_str,
+                    expected,
+                    found,
+                }).at(#pos) #error_path);
+            }
+        }
+    }
+}
+
+/// Size fields of a struct or a variant, with `len_of`, `count_of` or `size_of_self`
+struct SizeFields {
+    fields: Vec<SizeOf>,
+}
+
+impl SizeFields {
+    /// Find size fields and the fields they measure
+    fn new(fields: &Fields, attributes: &Attributes) -> Result<Self> {
+        let mut result = Vec::new();
+        for (index, field) in fields.iter().enumerate() {
+            let field_attributes = attributes.extend(&field.attrs)?;
+            let measured = match (&field_attributes.len_of, &field_attributes.count_of, field_attributes.size_of_self) {
+                (None, None, false) => continue,
+                (Some(name), None, false) | (None, Some(name), false) => {
+                    if field.ident.is_none() {
+                        return syn_error(name, "The measured field can only be taken from a named field");
+                    }
+                    let measured = fields.iter().position(|field| field.ident.as_ref() == Some(name));
+                    let measured = match measured {
+                        Some(measured) if measured > index => measured,
+                        _ => return syn_error(name, "The measured field must be a later field of this struct"),
+                    };
+                    match field_attributes.len_of {
+                        Some(_) => Measured::Len(measured),
+                        None => Measured::Count(measured),
+                    }
+                }
+                (None, None, true) => Measured::Whole,
+                _ => return syn_error(field, "Only one of len_of, count_of and size_of_self can be used"),
+            };
+            if field_attributes.bits.is_some() || field_attributes.skip || field_attributes.checksum.is_some() {
+                return syn_error(field, "A size field cannot be a bitfield, a checksum or be skipped");
+            }
+            let ty = match &field.ty {
+                Type::Path(type_path) if type_path.path.get_ident().is_some_and(primitive_type) => {
+                    type_path.path.get_ident().unwrap().clone()
+                }
+                _ => return syn_error(&field.ty, "A size field must be an integer"),
+            };
+            let (field_ident, field_str) = match &field.ident {
+                Some(ident) => (ident.clone(), ident.to_string()),
+                None => (Ident::new(&format!("field_{}", index), field.span()), index.to_string()),
+            };
+            result.push(SizeOf {
+                field_ident,
+                field_str,
+                ty,
+                index,
+                measured,
+                compute: None,
+            });
+        }
+        Ok(SizeFields { fields: result })
+    }
+
+    /// Reference to the value to write for field `index`, if it is a size field
+    fn field_ref(&self, index: usize) -> Option<TokenStream> {
+        self.fields.iter().find(|size_of| size_of.index == index).map(|size_of| {
+            let computed = size_of.computed();
+            quote! { (&#computed) }
+        })
+    }
+
+    /// Is field `index` measured by a size field
+    fn is_measured(&self, index: usize) -> bool {
+        self.fields.iter().any(|size_of| match size_of.measured {
+            Measured::Len(measured) | Measured::Count(measured) => measured == index,
+            Measured::Whole => false,
+        })
+    }
+
+    /// Called before the code of field `index`, after its padding
+    fn begin(&self, index: usize, read_code: &mut TokenStream) {
+        if self.is_measured(index) {
+            read_code.extend(quote! { let item_start = _pos; });
+        }
+    }
+
+    /// Called after the code of field `index`, `size_code` is the size of the fields before it and
+    /// `item_size_code` its own size
+    fn end(
+        &mut self,
+        index: usize,
+        field_ident: &Ident,
+        prefixed_field_ref: &TokenStream,
+        size_code: &TokenStream,
+        item_size_code: &TokenStream,
+        read_code: &mut TokenStream,
+        error_path: &TokenStream,
+    ) {
+        for size_of in self.fields.iter_mut() {
+            match size_of.measured {
+                Measured::Len(measured) if measured == index => {
+                    read_code.extend(size_of.check(quote! { _pos - item_start }, quote! { item_start }, error_path));
+                    size_of.compute = Some(quote! {{
+                        let mut _pos = self_start;
+                        #size_code
+                        let item_start = _pos;
+                        #item_size_code
+                        _pos - item_start
+                    }});
+                }
+                Measured::Count(measured) if measured == index => {
+                    read_code.extend(size_of.check(quote! { #field_ident.len() }, quote! { item_start }, error_path));
+                    size_of.compute = Some(quote! { #prefixed_field_ref.len() });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Add the code that computes size fields at the start of write code and the code that checks
+    /// the size of the whole struct or variant, which starts at `start`
+    fn finish(
+        self,
+        start: TokenStream,
+        size_code: &TokenStream,
+        read_code: &mut TokenStream,
+        write_code: &mut TokenStream,
+        error_path: &TokenStream,
+    ) {
+        if self.fields.is_empty() {
+            return;
+        }
+        let mut computed_code = quote! {
+            #[allow(unused_variables)]
+            let self_start = #start;
+        };
+        for size_of in self.fields.iter() {
+            let computed = size_of.computed();
+            let ty = &size_of.ty;
+            let field_str = &size_of.field_str;
+            let map_size_error = map_error(&quote! { .in_field(#field_str) #error_path });
+            // field measured are always generated after their size field
+            let compute = match &size_of.compute {
+                Some(compute) => compute.clone(),
+                None => {
+                    read_code.extend(size_of.check(quote! { _pos - self_start }, quote! { self_start }, error_path));
+                    quote! {{
+                        let mut _pos = self_start;
+                        #size_code
+                        _pos - self_start
+                    }}
+                }
+            };
+            computed_code.extend(quote! {
+                let #computed: #ty = plod::__private::SizeField::from_size(#compute) #map_size_error;
+            });
+        }
+        *read_code = quote! {
+            #[allow(unused_variables)]
+            let self_start = #start;
+            #read_code
+        };
+        *write_code = quote! {
+            #computed_code
+            #write_code
+        };
+    }
+}
+
+/// Generate code for a checksum field, it is checked on read and computed on write
+fn generate_for_checksum(
+    span: &ChecksumSpan,
+    field_ident: &Ident,
+    field_type: &Type,
+    prefixed_field_ref: &TokenStream,
+    prefixed_field_dotted: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    error_path: &TokenStream,
+    mode: Mode,
+) -> Result<()> {
+    let size = primitive_size(&span.ty);
+    // read like any integer
+    if span.embedded() && span.verify {
+        read_code.extend(quote! {
+            let checksum_offset = _pos - checksum_start;
+        });
+    }
+    let mut unused_write_code = TokenStream::new();
+    generate_for_item(
+        field_ident,
+        field_type,
+        prefixed_field_ref,
+        prefixed_field_dotted,
+        false,
+        attributes,
+        size_code,
+        read_code,
+        &mut unused_write_code,
+        &quote! { ctx },
+        &quote! { ctx },
+        error_path,
+        mode,
+    )?;
+    if !span.embedded() && span.verify {
+        read_code.extend(span.check(quote! { _pos - #size }));
+    }
+
+    // write the computed value, or a placeholder if it is not known yet
+    let computed = span.computed();
+    let bytes = if span.embedded() {
+        write_code.extend(quote! {
+            let checksum_offset = _pos - checksum_start;
+        });
+        quote! { [0; #size] }
+    } else {
+        to_bytes(span.endianness, quote! { #computed })
+    };
+    let write_buffer = mode.write_all(quote! { &buffer });
+    let map_item_error = map_error(error_path);
+    write_code.extend(quote! {
+        let buffer: [u8; #size] = #bytes;
+        #write_buffer #map_item_error;
+        _pos += #size;
+    });
+    Ok(())
+}
+
+/// Attributes of the field at index `i` among `count` fields
+fn field_attributes(attributes: &Attributes, field: &Field, i: usize, count: usize) -> Result<Attributes> {
+    let mut field_attributes = attributes.extend(&field.attrs)?;
+    // after the tag, the last field of an `other` variant keeps the rest of the data by default
+    if attributes.other && i > 0 && i + 1 == count && !Attributes::has_own_size(&field.attrs)? && has_size(&field.ty)? {
+        field_attributes.rest = true;
+    }
+    if field_attributes.rest && i + 1 != count {
+        return syn_error(field, "#[plod(rest)] must be the last field");
+    }
+    Ok(field_attributes)
+}
+
+/// Tell if a type can be given a size, ie: a `String`, a collection or a borrowed slice
+fn has_size(field_type: &Type) -> Result<bool> {
+    Ok(match field_type {
+        Type::Path(type_path) => type_path.path.is_ident("String") || collection_item(type_path)?.is_some(),
+        Type::Reference(reference) => matches!(reference.elem.as_ref(), Type::Slice(_) | Type::Path(_)),
+        _ => false,
+    })
+}
+
+/// Check that size attributes are not used on a type that has no size, ie: neither a `String`, a
+/// collection nor a borrowed slice
+fn check_unsized(field_type: &Type, attributes: &Attributes) -> Result<()> {
+    if let Some(size_field) = &attributes.size_from {
+        return syn_error(size_field, "count_from and bytes_from only work with Vec, other collections and String");
+    }
+    if attributes.rest {
+        return syn_error(field_type, "#[plod(rest)] only works with Vec, other collections, String and &[u8]");
+    }
+    check_no_terminator(attributes)?;
+    check_not_fixed(attributes)
+}
+
+/// Check that a fixed width is not used on a type that is not a `String`
+fn check_not_fixed(attributes: &Attributes) -> Result<()> {
+    match &attributes.fixed {
+        Some(fixed) => syn_error(fixed, "#[plod(fixed)] only works with String"),
+        None => Ok(()),
+    }
+}
+
+/// Check that a terminator is not used on a type that is not a collection
+fn check_no_terminator(attributes: &Attributes) -> Result<()> {
+    match &attributes.terminator {
+        Some(terminator) => syn_error(terminator, "#[plod(terminator)] only works with Vec and other collections"),
+        None => Ok(()),
+    }
+}
+
+/// Generate code for a single item of a variant or a struct
+fn generate_for_item(
+    field_ident: &Ident,
+    field_type: &Type,
+    prefixed_field_ref: &TokenStream,
+    prefixed_field_dotted: &TokenStream,
+    is_tag: bool,
     attributes: &Attributes,
     size_code: &mut TokenStream,
     read_code: &mut TokenStream,
     write_code: &mut TokenStream,
     context_val: &TokenStream,
     prefixed_context_val: &TokenStream,
+    error_path: &TokenStream,
+    mode: Mode,
 ) -> Result<()> {
+    let map_item_error = map_error(error_path);
+    let read_buffer = mode.read_exact(quote! { &mut buffer });
+    let write_buffer = mode.write_all(quote! { &buffer });
     if attributes.skip {
         // no size code, no write code
         // default on read
         read_code.extend(quote! {
-            let #field_ident = <#field_type as std::default::Default>::default();
+            let #field_ident = <#field_type as ::core::default::Default>::default();
         });
         return Ok(());
     }
+    if attributes.wire.is_some() {
+        return generate_for_wire(
+            field_ident,
+            field_type,
+            prefixed_field_ref,
+            is_tag,
+            attributes,
+            size_code,
+            read_code,
+            write_code,
+            context_val,
+            prefixed_context_val,
+            error_path,
+            mode,
+        );
+    }
+    if attributes.with.is_some()
+        || attributes.read_with.is_some()
+        || attributes.write_with.is_some()
+        || attributes.size_with.is_some()
+    {
+        return generate_for_codec(
+            field_ident,
+            field_type,
+            prefixed_field_ref,
+            prefixed_field_dotted,
+            is_tag,
+            attributes,
+            size_code,
+            read_code,
+            write_code,
+            context_val,
+            prefixed_context_val,
+            error_path,
+            mode,
+        );
+    }
+    if attributes.tag_from.is_some() {
+        return generate_for_tagged(
+            field_ident,
+            field_type,
+            prefixed_field_ref,
+            attributes,
+            size_code,
+            read_code,
+            write_code,
+            context_val,
+            prefixed_context_val,
+            error_path,
+            mode,
+        );
+    }
+    if attributes.condition.is_some() || attributes.presence.is_some() {
+        return generate_for_option(
+            field_ident,
+            field_type,
+            prefixed_field_ref,
+            attributes,
+            size_code,
+            read_code,
+            write_code,
+            context_val,
+            prefixed_context_val,
+            error_path,
+            mode,
+        );
+    }
     match field_type {
         Type::Path(type_path) => {
-            let mut is_vec = false;
             let mut is_primitive = false;
             if let Some(id) = type_path.path.segments.first() {
-                is_vec = id.ident == "Vec";
                 // TODO we should probably make sure there is only one segment
                 is_primitive = primitive_type(&id.ident);
             };
-            if is_vec {
+            let collection_item = collection_item(type_path)?;
+            if type_path.path.is_ident("String") {
+                generate_for_string(
+                    field_ident,
+                    prefixed_field_dotted,
+                    attributes,
+                    size_code,
+                    read_code,
+                    write_code,
+                    error_path,
+                    mode,
+                )?;
+            } else if let Some(item_type) = &collection_item {
                 generate_for_vec(
                     type_path,
+                    item_type,
                     field_ident,
                     prefixed_field_dotted,
                     attributes,
@@ -562,14 +2093,21 @@ fn generate_for_item(
                     write_code,
                     context_val,
                     prefixed_context_val,
+                    error_path,
+                    mode,
                 )?;
             } else if is_primitive {
+                check_unsized(field_type, attributes)?;
                 let ty = type_path.path.get_ident().unwrap();
                 let ty_size = primitive_size(ty);
-                let (from_method, to_method) = primitive_function(attributes.endianness);
-                size_code.extend(quote! {
-                    #ty_size +
-                });
+                let value = from_bytes(attributes.endianness, ty, quote! { buffer });
+                // without tag, the tag retained is stored apart
+                let stored = !(is_tag && attributes.untagged);
+                if stored {
+                    size_code.extend(quote! {
+                        _pos += #ty_size;
+                    });
+                }
                 if is_tag {
                     // TODO, tag should always be read/written by enum_impl, this would be easier
                     if let Some(diff) = &attributes.keep_diff {
@@ -584,8 +2122,8 @@ fn generate_for_item(
                 } else {
                     read_code.extend(quote! {
                         let mut buffer: [u8; #ty_size] = [0; #ty_size];
-                        from.read_exact(&mut buffer)?;
-                        let #field_ident = #ty::#from_method(buffer);
+                        #read_buffer #map_item_error;
+                        let #field_ident = #value;
                         _pos += #ty_size;
                     });
                 }
@@ -595,22 +2133,36 @@ fn generate_for_item(
                 } else {
                     TokenStream::new()
                 };
-                write_code.extend(quote! {
-                    let buffer: [u8; #ty_size] = (#prefixed_field_ref #diff). #to_method();
-                    to.write_all(&buffer)?;
-                    _pos += #ty_size;
+                let bytes = to_bytes(attributes.endianness, quote! { (#prefixed_field_ref #diff) });
+                if stored {
+                    write_code.extend(quote! {
+                        let buffer: [u8; #ty_size] = #bytes;
+                        #write_buffer #map_item_error;
+                        _pos += #ty_size;
+                    });
+                }
+            } else if mode == Mode::Borrow && has_lifetime(type_path) {
+                check_unsized(field_type, attributes)?;
+                // no size code, no write code
+                read_code.extend(quote! {
+                    let (#field_ident, rest) = <#type_path as plod::PlodBorrow<'_>>::impl_parse(*from, #context_val.into(), _pos) #map_item_error;
+                    _pos += from.len() - rest.len();
+                    *from = rest;
                 });
             } else {
+                check_unsized(field_type, attributes)?;
+                let read_item = mode.read_item(type_path, quote! { #context_val.into() });
+                let write_item = mode.write_item(type_path, prefixed_field_ref, quote! { #prefixed_context_val.into() });
                 size_code.extend(quote! {
-                    <#type_path as plod::Plod>::size_at_rest(#prefixed_field_ref) +
+                    _pos += <#type_path as plod::Plod>::size_at_pos(#prefixed_field_ref, _pos);
                 });
                 read_code.extend(quote! {
-                    let #field_ident = <#type_path as plod::Plod>::impl_read_from(from, #context_val.into(), _pos)?;
-                    _pos += <#type_path as plod::Plod>::size_at_rest(&#field_ident);
+                    let #field_ident = #read_item #map_item_error;
+                    _pos += <#type_path as plod::Plod>::size_at_pos(&#field_ident, _pos);
                 });
                 write_code.extend(quote! {
-                    <#type_path as plod::Plod>::impl_write_to(#prefixed_field_ref, to, #prefixed_context_val.into(), _pos)?;
-                    _pos += <#type_path as plod::Plod>::size_at_rest(#prefixed_field_ref);
+                    #write_item #map_item_error;
+                    _pos += <#type_path as plod::Plod>::size_at_pos(#prefixed_field_ref, _pos);
                 });
             }
         }
@@ -618,6 +2170,8 @@ fn generate_for_item(
             let mut field_list = TokenStream::new();
             for (i, field_ty) in t.elems.iter().enumerate() {
                 let field_ident = Ident::new(&format!("infield_{}", i), field_ty.span());
+                let field_str = i.to_string();
+                let field_error_path = quote! { .in_field(#field_str) #error_path };
                 let (prefixed_field_ref, prefixed_field_dotted) = {
                     let i = syn::Index::from(i);
                     (
@@ -637,6 +2191,8 @@ fn generate_for_item(
                     write_code,
                     context_val,
                     prefixed_context_val,
+                    &field_error_path,
+                    mode,
                 )?;
                 field_list.extend(quote! {
                     #field_ident,
@@ -658,15 +2214,19 @@ fn generate_for_item(
             }
 
             if vec_u8 {
+                let read_array = mode.read_exact(quote! { &mut #field_ident });
+                let write_array = mode.write_all(quote! { #prefixed_field_dotted as_slice() });
                 size_code.extend(quote! {
-                    #prefixed_field_dotted len() +
+                    _pos += #prefixed_field_dotted len();
                 });
                 read_code.extend(quote! {
                     let mut #field_ident: #t = [0; #n];
-                    from.read_exact(&mut #field_ident)?;
+                    #read_array #map_item_error;
+                    _pos += #n;
                 });
                 write_code.extend(quote! {
-                    to.write_all(#prefixed_field_dotted as_slice())?;
+                    #write_array #map_item_error;
+                    _pos += #n;
                 });
             } else {
                 let mut item_size_code = TokenStream::new();
@@ -685,25 +2245,78 @@ fn generate_for_item(
                     &mut item_write_code,
                     context_val,
                     prefixed_context_val,
+                    &quote! { .in_index(index) #error_path },
+                    mode,
                 )?;
                 size_code.extend(quote! {
-                    #prefixed_field_dotted iter().fold(0, |n, item| n + #item_size_code 0) +
+                    #prefixed_field_dotted iter().for_each(#[allow(unused_variables)] |item| { #item_size_code });
                 });
                 read_code.extend(quote! {
-                    let mut vec = Vec::new();
-                    for _ in 0..#n {
+                    let mut vec = plod::__private::Vec::new();
+                    for index in 0..#n {
                         #item_read_code
                         vec.push(item);
                     }
-                    let #field_ident: #t = vec.try_into().unwrap();
+                    // items don't need to implement Debug to unwrap
+                    let #field_ident: #t = match vec.try_into() {
+                        Ok(array) => array,
+                        Err(_) => unreachable!("exactly N items have been read"),
+                    };
                });
                 write_code.extend(quote! {
-                    for item in #prefixed_field_dotted iter() {
+                    for (index, item) in #prefixed_field_dotted iter().enumerate() {
                         #item_write_code
                     }
                 });
             }
         }
+        Type::Reference(reference) => {
+            if mode != Mode::Borrow {
+                return syn_error(field_type, "References are only supported with #[plod(borrow)]");
+            }
+            check_no_terminator(attributes)?;
+            check_not_fixed(attributes)?;
+            // borrowed types have neither size code nor write code
+            let mut read_size_code = if attributes.rest {
+                quote! { let size = from.len(); }
+            } else {
+                TokenStream::new()
+            };
+            match reference.elem.as_ref() {
+                Type::Slice(slice) if is_u8(&slice.elem) => {
+                    if !attributes.rest {
+                        read_size_code = read_size(field_type, attributes, mode, error_path)?;
+                    }
+                    read_code.extend(quote! {
+                        #read_size_code
+                        let #field_ident = plod::__private::take(from, size) #map_item_error;
+                        _pos += size;
+                    });
+                }
+                Type::Path(type_path) if type_path.path.is_ident("str") => {
+                    if attributes.encoding != TextEncoding::Utf8 {
+                        return syn_error(field_type, "Only UTF-8 strings can be borrowed");
+                    }
+                    if !attributes.rest {
+                        read_size_code = read_size(field_type, attributes, mode, error_path)?;
+                    }
+                    read_code.extend(quote! {
+                        #read_size_code
+                        let #field_ident = plod::__private::take_str(from, size) #map_item_error;
+                        _pos += size;
+                    });
+                }
+                Type::Array(array) if is_u8(&array.elem) => {
+                    check_unsized(field_type, attributes)?;
+                    let n = &array.len;
+                    read_code.extend(quote! {
+                        let #field_ident: #field_type = plod::__private::take(from, #n) #map_item_error .try_into().unwrap();
+                        _pos += #n;
+                    });
+                }
+                _ => return syn_error(field_type, "Only &[u8], &str and &[u8; N] can be borrowed"),
+            }
+        }
         _ => {
             return syn_error(field_ident, "Unsupported type for Plod");
         }
@@ -711,65 +2324,1007 @@ fn generate_for_item(
     Ok(())
 }
 
-fn generate_for_vec(
-    type_path: &TypePath,
-    field_ident: &Ident,
-    prefixed_field_dotted: &TokenStream,
+/// Generate code for padding bytes, `padding` is an expression that computes their number from `_pos`
+fn generate_padding(
+    padding: TokenStream,
     attributes: &Attributes,
     size_code: &mut TokenStream,
     read_code: &mut TokenStream,
     write_code: &mut TokenStream,
-    context_val: &TokenStream,
-    prefixed_context_val: &     TokenStream,
-) -> Result<()> {
-    let size_ty = match &attributes.size_type {
-        Some(ty) => ty,
-        None => {
-            return syn_error(
-                type_path,
-                "#[plod(size_type(<value>))] is mandatory for Vec<type>",
-            );
-        }
+    error_path: &TokenStream,
+    mode: Mode,
+) {
+    let fill = match &attributes.fill {
+        Some(fill) => quote! { #fill },
+        None => quote! { 0 },
     };
-    if !primitive_type(size_ty) {
-        return syn_error(size_ty, "vec length magic only works with primitive types");
-    }
-    let ty_size = primitive_size(size_ty);
-
-    let (from_method, to_method) = primitive_function(attributes.endianness);
-    // we can unwrap because it's how we know we are in a vec
-    let vec_generic = match &type_path.path.segments.first().unwrap().arguments {
-        PathArguments::AngleBracketed(pa) => {
-            if pa.args.len() != 1 {
-                return syn_error(
-                    type_path,
-                    "Plod only support regular Vec<Type>: unknown type Vec<X,Y,...>",
-                );
-            }
-            match pa.args.first().unwrap() {
-                GenericArgument::Type(t) => t,
-                _ => {
-                    return syn_error(
-                        type_path,
-                        "Plod only support regular Vec<Type>: unknown Vec<...>",
-                    )
-                }
+    let map_padding_error = map_error(error_path);
+    let read_buffer = mode.read_exact(quote! { &mut buffer });
+    let write_buffer = mode.write_all(quote! { &buffer });
+    let check = if attributes.check_padding {
+        quote! {
+            if buffer[0] != #fill {
+                return Err(plod::Error::new(plod::ErrorKind::BadPadding {
+                    expected: #fill,
+                    found: buffer[0],
+                }).at(_pos) #error_path);
             }
         }
-        _ => {
-            return syn_error(
-                type_path,
-                "Plod only support regular Vec<Type>: unknown Vec...",
-            );
-        }
+    } else {
+        TokenStream::new()
     };
-    // u8 special case
-    let mut vec_u8 = false;
-    if let Type::Path(type_path) = vec_generic {
-        if let Some(id) = type_path.path.segments.first() {
-            vec_u8 = id.ident == "u8";
+    size_code.extend(quote! {
+        _pos += #padding;
+    });
+    read_code.extend(quote! {
+        for _ in 0..#padding {
+            let mut buffer: [u8; 1] = [0; 1];
+            #read_buffer #map_padding_error;
+            #check
+            _pos += 1;
+        }
+    });
+    write_code.extend(quote! {
+        for _ in 0..#padding {
+            let buffer: [u8; 1] = [#fill; 1];
+            #write_buffer #map_padding_error;
+            _pos += 1;
+        }
+    });
+}
+
+/// Generate padding code for `#[plod(pad=..)]` and `#[plod(align=..)]`, in this order
+/// then for the C alignment of `repr_c_type` if any
+fn generate_item_padding(
+    attributes: &Attributes,
+    repr_c_type: Option<&Type>,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    error_path: &TokenStream,
+    mode: Mode,
+) -> Result<()> {
+    if let Some(pad) = &attributes.pad {
+        generate_padding(quote! { #pad }, attributes, size_code, read_code, write_code, error_path, mode);
+    }
+    if let Some(align) = &attributes.align {
+        generate_alignment(quote! { #align }, attributes, size_code, read_code, write_code, error_path, mode);
+    }
+    if let Some(ty) = repr_c_type {
+        let alignment = c_alignment(ty)?;
+        generate_alignment(alignment, attributes, size_code, read_code, write_code, error_path, mode);
+    }
+    Ok(())
+}
+
+/// Generate padding code so that the next item starts at a multiple of `alignment`
+fn generate_alignment(
+    alignment: TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    error_path: &TokenStream,
+    mode: Mode,
+) {
+    generate_padding(
+        quote! { (_pos.next_multiple_of(#alignment) - _pos) },
+        attributes,
+        size_code,
+        read_code,
+        write_code,
+        error_path,
+        mode,
+    );
+}
+
+/// Alignment of a field type in a `#[plod(repr_c)]` struct
+fn c_alignment(ty: &Type) -> Result<TokenStream> {
+    match ty {
+        Type::Path(type_path) if collection_item(type_path)?.is_some() => syn_error(
+            ty,
+            "Collections have no C representation, they cannot be used with #[plod(repr_c)]",
+        ),
+        Type::Reference(_) => syn_error(ty, "References cannot be used with #[plod(repr_c)]"),
+        _ => Ok(quote! { <#ty as plod::Plod>::ALIGNMENT }),
+    }
+}
+
+/// Consecutive `#[plod(bits=..)]` fields, they are stored in a single integer, the storage unit
+struct BitGroup {
+    /// storage unit, the type of the first field (`u8` for `bool`)
+    unit: Ident,
+    /// size of the storage unit in bits
+    unit_bits: u32,
+    /// bits already used by fields
+    used_bits: u32,
+    /// attributes of the first field, they define endianness and bit order
+    attributes: Attributes,
+    /// first field, to report errors
+    first_field: Ident,
+    /// error path of the first field, used for storage unit errors
+    error_path: TokenStream,
+    /// field identifier, type, reference to the field, shift, bits and error path
+    fields: Vec<(Ident, Type, TokenStream, u32, u32, TokenStream)>,
+}
+
+/// Add a bitfield to the current group, code is generated once the storage unit is full
+fn generate_for_bitfield(
+    bit_group: &mut Option<BitGroup>,
+    field_ident: &Ident,
+    field_type: &Type,
+    prefixed_field_ref: &TokenStream,
+    attributes: &Attributes,
+    repr_c: bool,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    error_path: &TokenStream,
+    mode: Mode,
+) -> Result<()> {
+    if let Some(wire) = &attributes.wire {
+        return syn_error(wire, "#[plod(wire=<type>)] cannot be used with bits");
+    }
+    check_unsized(field_type, attributes)?;
+    // all checked by attribute parsing
+    let bits_lit = attributes.bits.as_ref().unwrap();
+    let bits = bits_lit.base10_parse::<u32>()?;
+    let type_ident = match field_type {
+        Type::Path(type_path) => type_path.path.get_ident(),
+        _ => None,
+    };
+    let (unit, type_bits) = match type_ident {
+        Some(ty) if ty == "bool" => (Ident::new("u8", ty.span()), 1),
+        Some(ty) if primitive_type(ty) && !ty.to_string().starts_with('f') => {
+            let size = primitive_size(ty).base10_parse::<u32>()?;
+            (ty.clone(), size * 8)
+        }
+        _ => return syn_error(field_type, "#[plod(bits=<value>)] only works with integers and bool"),
+    };
+    if bits > type_bits {
+        return syn_error(bits_lit, "This bitfield is larger than its type");
+    }
+
+    match bit_group {
+        None => {
+            // the first field defines the storage unit and is padded like the unit
+            generate_item_padding(
+                attributes,
+                repr_c.then_some(field_type),
+                size_code,
+                read_code,
+                write_code,
+                error_path,
+                mode,
+            )?;
+            let unit_bits = primitive_size(&unit).base10_parse::<u32>()? * 8;
+            *bit_group = Some(BitGroup {
+                unit,
+                unit_bits,
+                used_bits: 0,
+                attributes: attributes.clone(),
+                first_field: field_ident.clone(),
+                error_path: error_path.clone(),
+                fields: Vec::new(),
+            });
+        }
+        Some(_) => {
+            if attributes.pad.is_some() || attributes.align.is_some() {
+                return syn_error(field_ident, "Padding cannot be inserted inside a bitfield storage unit");
+            }
+        }
+    }
+    let group = bit_group.as_mut().unwrap();
+    if group.used_bits + bits > group.unit_bits {
+        return syn_error(
+            bits_lit,
+            &format!("Bitfields overflow their storage unit of {} bits", group.unit_bits),
+        );
+    }
+    let shift = if group.attributes.lsb_first {
+        group.used_bits
+    } else {
+        group.unit_bits - group.used_bits - bits
+    };
+    group.used_bits += bits;
+    group.fields.push((
+        field_ident.clone(),
+        field_type.clone(),
+        prefixed_field_ref.clone(),
+        shift,
+        bits,
+        error_path.clone(),
+    ));
+    if group.used_bits < group.unit_bits {
+        return Ok(());
+    }
+
+    // the storage unit is full
+    let group = bit_group.take().unwrap();
+    let unit = &group.unit;
+    let unit_size = primitive_size(unit);
+    let unit_value = from_bytes(group.attributes.endianness, unit, quote! { buffer });
+    let unit_bytes = to_bytes(
+        group.attributes.endianness,
+        quote! { <#unit as plod::__private::BitUnit>::from_bits(bit_unit) },
+    );
+    let map_unit_error = map_error(&group.error_path);
+    let read_buffer = mode.read_exact(quote! { &mut buffer });
+    let write_buffer = mode.write_all(quote! { &buffer });
+    let mut field_reads = TokenStream::new();
+    let mut field_writes = Vec::new();
+    for (ident, ty, field_ref, shift, bits, error_path) in group.fields.iter() {
+        let map_field_error = map_error(error_path);
+        field_reads.extend(quote! {
+            let #ident = <#ty as plod::__private::Bitfield>::from_bits(bit_unit, #shift, #bits) #map_field_error;
+        });
+        field_writes.push(quote! {
+            <#ty as plod::__private::Bitfield>::to_bits(#field_ref, #shift, #bits) #map_field_error
+        });
+    }
+    size_code.extend(quote! {
+        _pos += #unit_size;
+    });
+    read_code.extend(quote! {
+        let mut buffer: [u8; #unit_size] = [0; #unit_size];
+        #read_buffer #map_unit_error;
+        let bit_unit = plod::__private::BitUnit::to_bits(#unit_value);
+        #field_reads
+        _pos += #unit_size;
+    });
+    write_code.extend(quote! {
+        let bit_unit = #(#field_writes)|*;
+        let buffer: [u8; #unit_size] = #unit_bytes;
+        #write_buffer #map_unit_error;
+        _pos += #unit_size;
+    });
+    Ok(())
+}
+
+/// Bitfields must fill their storage unit before any other field
+fn check_bit_group(bit_group: &Option<BitGroup>) -> Result<()> {
+    match bit_group {
+        Some(group) => syn_error(
+            &group.first_field,
+            &format!("Bitfields must fill their storage unit of {} bits", group.unit_bits),
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Generate code that reads the size of a `Vec` or a borrowed slice into `size`
+fn read_size<S: Spanned>(
+    span: &S,
+    attributes: &Attributes,
+    mode: Mode,
+    error_path: &TokenStream,
+) -> Result<TokenStream> {
+    let size_ty = match &attributes.size_type {
+        Some(ty) => ty,
+        None => {
+            return syn_error(span, "#[plod(size_type(<value>))] is mandatory for Vec<type> and String");
         }
+    };
+    if !primitive_type(size_ty) {
+        return syn_error(size_ty, "vec length magic only works with primitive types");
+    }
+    let ty_size = primitive_size(size_ty);
+    let size_value = from_bytes(attributes.endianness, size_ty, quote! { buffer });
+    let minus_one = if attributes.size_is_next {
+        quote! { - 1 }
+    } else {
+        quote! {}
+    };
+    let read_buffer = mode.read_exact(quote! { &mut buffer });
+    let map_size_error = map_error(error_path);
+    Ok(quote! {
+        let mut buffer: [u8; #ty_size] = [0; #ty_size];
+        #read_buffer #map_size_error;
+        _pos += #ty_size;
+        let size = #size_value as usize #minus_one;
+    })
+}
+
+/// Generate code for a field stored as its `wire` type, it is converted with `From` or `TryFrom` on
+/// read and with `Into` or `TryInto` on write
+fn generate_for_wire(
+    field_ident: &Ident,
+    field_type: &Type,
+    prefixed_field_ref: &TokenStream,
+    is_tag: bool,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    context_val: &TokenStream,
+    prefixed_context_val: &TokenStream,
+    error_path: &TokenStream,
+    mode: Mode,
+) -> Result<()> {
+    // checked by generate_for_item
+    let wire = attributes.wire.as_ref().unwrap();
+    if !primitive_type(wire) {
+        return syn_error(wire, "#[plod(wire=<type>)] only works with primitive types");
     }
+    let wire_type = Type::Path(TypePath {
+        qself: None,
+        path: wire.clone().into(),
+    });
+    let wire_size = primitive_size(wire);
+    let type_str = quote! { #field_type }.to_string().replace(' ', "");
+    let wire_str = wire.to_string();
+
+    // the wire value is read and written like any primitive
+    let wire_attributes = Attributes {
+        wire: None,
+        ..attributes.clone()
+    };
+    let mut wire_write_code = TokenStream::new();
+    generate_for_item(
+        field_ident,
+        &wire_type,
+        &quote! { (&wire_value) },
+        &quote! { wire_value. },
+        is_tag,
+        &wire_attributes,
+        size_code,
+        read_code,
+        &mut wire_write_code,
+        context_val,
+        prefixed_context_val,
+        error_path,
+        mode,
+    )?;
+
+    if attributes.try_from {
+        read_code.extend(quote! {
+            let #field_ident = <#field_type as ::core::convert::TryFrom<#wire>>::try_from(#field_ident).map_err(|_| {
+                plod::Error::new(plod::ErrorKind::InvalidValue {
+                    type_name: #type_str,
+                    value: plod::__private::ToString::to_string(&#field_ident),
+                }).at(_pos - #wire_size) #error_path
+            })?;
+        });
+    } else {
+        read_code.extend(quote! {
+            let #field_ident = <#field_type as ::core::convert::From<#wire>>::from(#field_ident);
+        });
+    }
+    let value = quote! { ::core::clone::Clone::clone(#prefixed_field_ref) };
+    if attributes.try_into {
+        write_code.extend(quote! {
+            let wire_value = <#field_type as ::core::convert::TryInto<#wire>>::try_into(#value).map_err(|_| {
+                plod::Error::new(plod::ErrorKind::InvalidConversion {
+                    from: #type_str,
+                    to: #wire_str,
+                }).at(_pos) #error_path
+            })?;
+        });
+    } else {
+        write_code.extend(quote! {
+            let wire_value = <#field_type as ::core::convert::Into<#wire>>::into(#value);
+        });
+    }
+    write_code.extend(wire_write_code);
+    Ok(())
+}
+
+/// Generate code for a field with custom functions, given by `with`, `read_with`, `write_with` or
+/// `size_with`, the other ones are generated like any item
+fn generate_for_codec(
+    field_ident: &Ident,
+    field_type: &Type,
+    prefixed_field_ref: &TokenStream,
+    prefixed_field_dotted: &TokenStream,
+    is_tag: bool,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    context_val: &TokenStream,
+    prefixed_context_val: &TokenStream,
+    error_path: &TokenStream,
+    mode: Mode,
+) -> Result<()> {
+    // an individual function overrides the one from the module
+    let function = |own: &Option<Path>, name: &str| -> Option<Path> {
+        own.clone().or_else(|| {
+            attributes.with.as_ref().map(|module| {
+                let mut function = module.clone();
+                function.segments.push(Ident::new(name, module.span()).into());
+                function
+            })
+        })
+    };
+    let read_function = function(&attributes.read_with, "read");
+    let write_function = function(&attributes.write_with, "write");
+    let size_function = function(&attributes.size_with, "size");
+
+    let mut item_size_code = TokenStream::new();
+    let mut item_read_code = TokenStream::new();
+    let mut item_write_code = TokenStream::new();
+    if read_function.is_none() || write_function.is_none() || size_function.is_none() {
+        let item_attributes = Attributes {
+            with: None,
+            read_with: None,
+            write_with: None,
+            size_with: None,
+            ..attributes.clone()
+        };
+        generate_for_item(
+            field_ident,
+            field_type,
+            prefixed_field_ref,
+            prefixed_field_dotted,
+            is_tag,
+            &item_attributes,
+            &mut item_size_code,
+            &mut item_read_code,
+            &mut item_write_code,
+            context_val,
+            prefixed_context_val,
+            error_path,
+            mode,
+        )?;
+    }
+
+    // without a size function, the type must implement Plod
+    let size_of = |value: TokenStream| match &size_function {
+        Some(function) => quote! { #function(#value, _pos) },
+        None => quote! { <#field_type as plod::Plod>::size_at_pos(#value, _pos) },
+    };
+    let map_item_error = map_error(error_path);
+    match &size_function {
+        Some(_) => {
+            let size = size_of(prefixed_field_ref.clone());
+            size_code.extend(quote! { _pos += #size; });
+        }
+        None => size_code.extend(item_size_code),
+    }
+    match &read_function {
+        Some(function) => {
+            let read = mode.read_with(function, context_val);
+            let size = size_of(quote! { &#field_ident });
+            read_code.extend(quote! {
+                let #field_ident: #field_type = #read #map_item_error;
+                _pos += #size;
+            });
+        }
+        None => read_code.extend(item_read_code),
+    }
+    match &write_function {
+        Some(function) => {
+            let write = mode.write_with(function, prefixed_field_ref, prefixed_context_val);
+            let size = size_of(prefixed_field_ref.clone());
+            write_code.extend(quote! {
+                #write #map_item_error;
+                _pos += #size;
+            });
+        }
+        None => write_code.extend(item_write_code),
+    }
+    Ok(())
+}
+
+/// Generate code for an enum whose tag is the value of `tag_from`, defined by generate_for_fields
+fn generate_for_tagged(
+    field_ident: &Ident,
+    field_type: &Type,
+    prefixed_field_ref: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    context_val: &TokenStream,
+    prefixed_context_val: &TokenStream,
+    error_path: &TokenStream,
+    mode: Mode,
+) -> Result<()> {
+    let type_path = match field_type {
+        Type::Path(type_path) if !(mode == Mode::Borrow && has_lifetime(type_path)) => type_path,
+        _ => return syn_error(field_type, "#[plod(tag_from=<field>)] only works with Plod enums"),
+    };
+    let map_item_error = map_error(error_path);
+    let read_item = mode.read_untagged(type_path, &quote! { tag_from }, quote! { #context_val.into() });
+    let write_item = mode.write_untagged(
+        type_path,
+        prefixed_field_ref,
+        &quote! { tag_from },
+        quote! { #prefixed_context_val.into() },
+    );
+    size_code.extend(quote! {
+        _pos += <#type_path as plod::PlodTagged>::untagged_size_at_pos(#prefixed_field_ref, _pos);
+    });
+    let write_tagged = quote! {
+        #write_item #map_item_error;
+        _pos += <#type_path as plod::PlodTagged>::untagged_size_at_pos(#prefixed_field_ref, _pos);
+    };
+    let size_field = match &attributes.size_from {
+        None => {
+            write_code.extend(write_tagged);
+            read_code.extend(quote! {
+                let #field_ident = #read_item #map_item_error;
+                _pos += <#type_path as plod::PlodTagged>::untagged_size_at_pos(&#field_ident, _pos);
+            });
+            return Ok(());
+        }
+        Some(size_field) if attributes.byte_sized => size_field.to_string(),
+        Some(size_field) => return syn_error(size_field, "The size of a tagged enum can only be given with bytes_from"),
+    };
+
+    // with bytes_from, the variant is read from a slice that contains exactly its bytes, so that an
+    // `other` variant stops at its end
+    let mut size_read_code = TokenStream::new();
+    let mut size_write_code = TokenStream::new();
+    size_from_code(attributes, &mut size_read_code, &mut size_write_code, error_path);
+    let data = if mode == Mode::Borrow {
+        quote! { let mut tagged_data = plod::__private::take(from, size) #map_item_error; }
+    } else {
+        // the buffer grows with the data read, the size found may be wrong
+        let read_data = mode.read_up_to(quote! { &mut tagged_buffer }, quote! { size });
+        quote! {
+            let mut tagged_buffer = plod::__private::Vec::new();
+            if #read_data #map_item_error < size {
+                return Err(plod::Error::new(plod::ErrorKind::UnexpectedEof).at(_pos) #error_path);
+            }
+            let mut tagged_data = tagged_buffer.as_slice();
+        }
+    };
+    read_code.extend(quote! {
+        #size_read_code
+        #data
+        let #field_ident = {
+            let from = &mut tagged_data;
+            #read_item #map_item_error
+        };
+        if !tagged_data.is_empty() {
+            return Err(plod::Error::new(plod::ErrorKind::SizeMismatch {
+                size_field: #size_field,
+                expected: size,
+                found: size - tagged_data.len(),
+            }).at(_pos) #error_path);
+        }
+        _pos += size;
+    });
+    write_code.extend(quote! {
+        let size = <#type_path as plod::PlodTagged>::untagged_size_at_pos(#prefixed_field_ref, _pos);
+        #size_write_code
+        #write_tagged
+    });
+    Ok(())
+}
+
+/// Generate code for an `Option` that is present if `condition` is true, `condition` is either
+/// defined by generate_for_fields or by a presence marker
+fn generate_for_option(
+    field_ident: &Ident,
+    field_type: &Type,
+    prefixed_field_ref: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    context_val: &TokenStream,
+    prefixed_context_val: &TokenStream,
+    error_path: &TokenStream,
+    mode: Mode,
+) -> Result<()> {
+    let inner_type = match field_type {
+        Type::Path(type_path) if type_path.path.segments.len() == 1 => {
+            let segment = type_path.path.segments.first().unwrap();
+            match &segment.arguments {
+                PathArguments::AngleBracketed(pa) if segment.ident == "Option" => {
+                    match pa.args.first() {
+                        Some(GenericArgument::Type(t)) if pa.args.len() == 1 => Some(t),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    let inner_type = match inner_type {
+        Some(t) => t,
+        None => {
+            return syn_error(
+                field_type,
+                "#[plod(if)] and #[plod(presence)] can only be used on Option<Type>",
+            )
+        }
+    };
+    if let Some(ty) = &attributes.presence {
+        if attributes.condition.is_some() {
+            return syn_error(ty, "#[plod(presence)] cannot be used with #[plod(if)]");
+        }
+        if !primitive_type(ty) {
+            return syn_error(ty, "presence only works with primitive types");
+        }
+        let ty_size = primitive_size(ty);
+        let presence_value = from_bytes(attributes.endianness, ty, quote! { buffer });
+        let presence_bytes = to_bytes(attributes.endianness, quote! { presence });
+        let present = match &attributes.present {
+            Some(lit) => quote! { #lit },
+            None => quote! { 1 },
+        };
+        let absent = match &attributes.absent {
+            Some(lit) => quote! { #lit },
+            None => quote! { 0 },
+        };
+        let map_presence_error = map_error(error_path);
+        let read_buffer = mode.read_exact(quote! { &mut buffer });
+        let write_buffer = mode.write_all(quote! { &buffer });
+        size_code.extend(quote! {
+            _pos += #ty_size;
+        });
+        read_code.extend(quote! {
+            let mut buffer: [u8; #ty_size] = [0; #ty_size];
+            #read_buffer #map_presence_error;
+            let presence = #presence_value;
+            let (present, absent): (#ty, #ty) = (#present, #absent);
+            let condition = if presence == present {
+                true
+            } else if presence == absent {
+                false
+            } else {
+                return Err(plod::Error::new(plod::ErrorKind::InvalidValue {
+                    type_name: "Option",
+                    value: plod::__private::ToString::to_string(&presence),
+                }).at(_pos) #error_path);
+            };
+            _pos += #ty_size;
+        });
+        write_code.extend(quote! {
+            let condition = #prefixed_field_ref.is_some();
+            let presence: #ty = if condition { #present } else { #absent };
+            let buffer: [u8; #ty_size] = #presence_bytes;
+            #write_buffer #map_presence_error;
+            _pos += #ty_size;
+        });
+    }
+    let inner_attributes = Attributes {
+        condition: None,
+        presence: None,
+        ..attributes.clone()
+    };
+    let mut inner_size_code = TokenStream::new();
+    let mut inner_read_code = TokenStream::new();
+    let mut inner_write_code = TokenStream::new();
+    generate_for_item(
+        field_ident,
+        inner_type,
+        &quote! { #field_ident },
+        &quote! { #field_ident . },
+        false,
+        &inner_attributes,
+        &mut inner_size_code,
+        &mut inner_read_code,
+        &mut inner_write_code,
+        context_val,
+        prefixed_context_val,
+        error_path,
+        mode,
+    )?;
+    size_code.extend(quote! {
+        if let Some(#field_ident) = #prefixed_field_ref {
+            #inner_size_code
+        }
+    });
+    read_code.extend(quote! {
+        let #field_ident = if condition {
+            #inner_read_code
+            Some(#field_ident)
+        } else {
+            None
+        };
+    });
+    write_code.extend(quote! {
+        match (condition, #prefixed_field_ref) {
+            (true, Some(#field_ident)) => {
+                #inner_write_code
+            }
+            (false, None) => {}
+            _ => {
+                return Err(plod::Error::new(plod::ErrorKind::OptionMismatch { condition }).at(_pos) #error_path);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Generate the code that writes `size` before a `Vec`, it is the counterpart of `read_size`
+fn write_size<S: Spanned>(
+    span: &S,
+    attributes: &Attributes,
+    mode: Mode,
+    error_path: &TokenStream,
+) -> Result<TokenStream> {
+    let size_ty = match &attributes.size_type {
+        Some(ty) => ty,
+        None => {
+            return syn_error(span, "#[plod(size_type(<value>))] is mandatory for Vec<type> and String");
+        }
+    };
+    if !primitive_type(size_ty) {
+        return syn_error(size_ty, "vec length magic only works with primitive types");
+    }
+    let ty_size = primitive_size(size_ty);
+    let plus_one = if attributes.size_is_next {
+        quote! { + 1 }
+    } else {
+        quote! {}
+    };
+    let size_bytes = to_bytes(attributes.endianness, quote! { (size as #size_ty #plus_one) });
+    let write_buffer = mode.write_all(quote! { &buffer });
+    let map_size_error = map_error(error_path);
+    Ok(quote! {
+        let buffer: [u8; #ty_size] = #size_bytes;
+        #write_buffer #map_size_error;
+        _pos += #ty_size;
+    })
+}
+
+/// Generate the code that reads `size_from` for `count_from` and `bytes_from`, and checks it on write
+fn size_from_code(
+    attributes: &Attributes,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    error_path: &TokenStream,
+) {
+    let map_size_error = map_error(error_path);
+    // size_from is defined by generate_for_fields
+    let size_field = attributes.size_from.as_ref().unwrap().to_string();
+    read_code.extend(quote! {
+        let size = plod::__private::SizeField::to_size(size_from) #map_size_error;
+    });
+    write_code.extend(quote! {
+        let expected = plod::__private::SizeField::to_size(size_from) #map_size_error;
+        if size != expected {
+            return Err(plod::Error::new(plod::ErrorKind::SizeMismatch {
+                size_field: #size_field,
+                expected,
+                found: size,
+            }).at(_pos) #error_path);
+        }
+    });
+}
+
+/// Generate code for a `String`, stored like a `Vec<u8>`, as a C string or with a fixed size.
+/// Sizes are counted in code units of the encoding, or in bytes with `byte_sized`.
+fn generate_for_string(
+    field_ident: &Ident,
+    prefixed_field_dotted: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    error_path: &TokenStream,
+    mode: Mode,
+) -> Result<()> {
+    check_no_terminator(attributes)?;
+    let map_string_error = map_error(error_path);
+    let read_buffer = mode.read_exact(quote! { &mut buffer });
+    let write_buffer = mode.write_all(quote! { &buffer });
+    let write_encoded = mode.write_all(quote! { &encoded });
+    let encoding = match (attributes.encoding, attributes.endianness) {
+        (TextEncoding::Utf8, _) => quote! { plod::__private::Encoding::Utf8 },
+        (TextEncoding::Ascii, _) => quote! { plod::__private::Encoding::Ascii },
+        (TextEncoding::Latin1, _) => quote! { plod::__private::Encoding::Latin1 },
+        (TextEncoding::Utf16Be, _) | (TextEncoding::Utf16, Endianness::Big) => {
+            quote! { plod::__private::Encoding::Utf16Be }
+        }
+        (TextEncoding::Utf16Le, _) | (TextEncoding::Utf16, Endianness::Little) => {
+            quote! { plod::__private::Encoding::Utf16Le }
+        }
+        (TextEncoding::Utf16, Endianness::Native) => quote! { plod::__private::Encoding::Utf16Ne },
+        (TextEncoding::Utf16, Endianness::Dynamic) => quote! {
+            match byte_order {
+                plod::ByteOrder::Big => plod::__private::Encoding::Utf16Be,
+                plod::ByteOrder::Little => plod::__private::Encoding::Utf16Le,
+            }
+        },
+    };
+    let unit: usize = match attributes.encoding {
+        TextEncoding::Utf16 | TextEncoding::Utf16Be | TextEncoding::Utf16Le => 2,
+        _ => 1,
+    };
+    let lossy = attributes.lossy;
+    write_code.extend(quote! {
+        let encoded = plod::__private::encode(#prefixed_field_dotted as_str(), #encoding, #lossy) #map_string_error;
+    });
+    if let Some(n) = &attributes.fixed {
+        if let Some(pad) = &attributes.pad {
+            return syn_error(pad, "#[plod(pad)] cannot be used with fixed, the padding byte is set with #[plod(fill=<byte>)]");
+        }
+        let fill = match &attributes.fill {
+            Some(fill) => quote! { #fill },
+            None => quote! { 0 },
+        };
+        size_code.extend(quote! {
+            _pos += #n;
+        });
+        read_code.extend(quote! {
+            let mut buffer = [0_u8; #n];
+            #read_buffer #map_string_error;
+            let #field_ident = plod::__private::decode_fixed(&buffer, #fill, #encoding, #lossy) #map_string_error;
+            _pos += #n;
+        });
+        write_code.extend(quote! {
+            plod::__private::check_fixed(&encoded, #n) #map_string_error;
+            let mut buffer = [#fill; #n];
+            buffer[..encoded.len()].copy_from_slice(&encoded);
+            #write_buffer #map_string_error;
+            _pos += #n;
+        });
+        return Ok(());
+    }
+    let mut write_terminator = TokenStream::new();
+    let encoded_len = quote! { plod::__private::encoded_len(#prefixed_field_dotted as_str(), #encoding) };
+    if attributes.cstr && !attributes.rest && attributes.size_from.is_none() {
+        size_code.extend(quote! {
+            _pos += #encoded_len + #unit;
+        });
+        read_code.extend(quote! {
+            let mut vec_buffer = plod::__private::Vec::new();
+            loop {
+                let mut buffer = [0_u8; #unit];
+                #read_buffer #map_string_error;
+                if buffer == [0_u8; #unit] {
+                    break;
+                }
+                vec_buffer.extend_from_slice(&buffer);
+            }
+            let size = vec_buffer.len() + #unit;
+        });
+        write_code.extend(quote! {
+            plod::__private::check_cstr(#prefixed_field_dotted as_str()) #map_string_error;
+        });
+        write_terminator = quote! {
+            let buffer = [0_u8; #unit];
+            #write_buffer #map_string_error;
+            _pos += #unit;
+        };
+    } else if attributes.rest {
+        let read_rest = mode.read_to_end(quote! { &mut vec_buffer });
+        size_code.extend(quote! {
+            _pos += #encoded_len;
+        });
+        read_code.extend(quote! {
+            let mut vec_buffer = plod::__private::Vec::new();
+            let size = #read_rest #map_string_error;
+        });
+    } else {
+        // the size is a number of code units unless it is in bytes
+        let (to_count, to_bytes) = if unit == 1 || attributes.byte_sized {
+            (TokenStream::new(), TokenStream::new())
+        } else {
+            (quote! { / #unit }, quote! { .saturating_mul(#unit) })
+        };
+        write_code.extend(quote! {
+            let size = encoded.len() #to_count;
+        });
+        if attributes.size_from.is_some() {
+            size_from_code(attributes, read_code, write_code, error_path);
+        } else {
+            read_code.extend(read_size(field_ident, attributes, mode, error_path)?);
+            write_code.extend(write_size(field_ident, attributes, mode, error_path)?);
+            // checked by read_size
+            let ty_size = primitive_size(attributes.size_type.as_ref().unwrap());
+            size_code.extend(quote! {
+                _pos += #ty_size;
+            });
+        }
+        // the buffer grows with the data read, the size found may be wrong
+        let read_vec = mode.read_up_to(quote! { &mut vec_buffer }, quote! { size });
+        size_code.extend(quote! {
+            _pos += #encoded_len;
+        });
+        read_code.extend(quote! {
+            let size = size #to_bytes;
+            let mut vec_buffer = plod::__private::Vec::new();
+            if #read_vec #map_string_error < size {
+                return Err(plod::Error::new(plod::ErrorKind::UnexpectedEof).at(_pos) #error_path);
+            }
+        });
+    }
+    read_code.extend(quote! {
+        let #field_ident = plod::__private::decode(vec_buffer, #encoding, #lossy) #map_string_error;
+        _pos += size;
+    });
+    write_code.extend(quote! {
+        #write_encoded #map_string_error;
+        _pos += encoded.len();
+        #write_terminator
+    });
+    Ok(())
+}
+
+/// Item type of a collection that is stored like a `Vec`, maps contain `(key, value)` tuples.
+/// Returns `None` if this is not a supported collection.
+fn collection_item(type_path: &TypePath) -> Result<Option<Type>> {
+    let segment = match type_path.path.segments.last() {
+        Some(segment) => segment,
+        None => return Ok(None),
+    };
+    let name = segment.ident.to_string();
+    let expected = match name.as_str() {
+        "Vec" | "VecDeque" | "BTreeSet" | "HashSet" | "Box" => 1,
+        "BTreeMap" | "HashMap" => 2,
+        _ => return Ok(None),
+    };
+    let args: Vec<&GenericArgument> = match &segment.arguments {
+        PathArguments::AngleBracketed(pa) => pa.args.iter().collect(),
+        _ => Vec::new(),
+    };
+    match args.as_slice() {
+        // other boxes are regular Plod types
+        [GenericArgument::Type(Type::Slice(slice))] if name == "Box" => Ok(Some((*slice.elem).clone())),
+        _ if name == "Box" => Ok(None),
+        [GenericArgument::Type(t)] if expected == 1 => Ok(Some(t.clone())),
+        [GenericArgument::Type(k), GenericArgument::Type(v)] if expected == 2 => {
+            Ok(Some(syn::parse_quote! { (#k, #v) }))
+        }
+        _ => syn_error(
+            type_path,
+            &format!("Plod only support regular {} with {} type parameter(s)", name, expected),
+        ),
+    }
+}
+
+/// Generate code for a `Vec` or any collection stored like a `Vec`, see `collection_item`
+fn generate_for_vec(
+    type_path: &TypePath,
+    vec_generic: &Type,
+    field_ident: &Ident,
+    prefixed_field_dotted: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    context_val: &TokenStream,
+    prefixed_context_val: &TokenStream,
+    error_path: &TokenStream,
+    mode: Mode,
+) -> Result<()> {
+    check_not_fixed(attributes)?;
+    let map_vec_error = map_error(error_path);
+    let read_vec = mode.read_up_to(quote! { &mut #field_ident }, quote! { size });
+    let write_vec = mode.write_all(quote! { #prefixed_field_dotted as_slice() });
+    if attributes.terminator.is_some() && (attributes.rest || attributes.size_from.is_some()) {
+        return syn_error(
+            type_path,
+            "#[plod(terminator)] cannot be used with rest, count_from or bytes_from",
+        );
+    }
+    // the size is either stored in a prefix or in an earlier field, or there is no size
+    let ty_size = match &attributes.size_from {
+        _ if attributes.rest || attributes.terminator.is_some() => None,
+        Some(_) => None,
+        None => {
+            let size_ty = match &attributes.size_type {
+                Some(ty) => ty,
+                None => {
+                    return syn_error(
+                        type_path,
+                        "#[plod(size_type(<value>))] is mandatory for Vec<type>",
+                    );
+                }
+            };
+            if !primitive_type(size_ty) {
+                return syn_error(size_ty, "vec length magic only works with primitive types");
+            }
+            Some(primitive_size(size_ty))
+        }
+    };
+    let prefix_size = match &ty_size {
+        Some(ty_size) => quote! { #ty_size },
+        None => quote! { 0 },
+    };
+
+    // other collections are read into a Vec and converted
+    let is_vec = type_path.path.segments.last().is_some_and(|s| s.ident == "Vec");
+    // u8 special case, terminated Vec<u8> are read item by item
+    let vec_u8 = is_vec && is_u8(vec_generic) && attributes.terminator.is_none();
 
     let mut item_size_code = TokenStream::new();
     let mut item_read_code = TokenStream::new();
@@ -777,93 +3332,222 @@ fn generate_for_vec(
     let item_name = Ident::new("item", field_ident.span());
     let it_name = Ident::new("it", field_ident.span());
 
+    if ty_size.is_some() {
+        size_code.extend(quote! {
+            _pos += #prefix_size;
+        });
+    }
     if vec_u8 {
         size_code.extend(quote! {
-            #ty_size + #prefixed_field_dotted len() +
+            _pos += #prefixed_field_dotted len();
         });
     } else {
+        // items have their own size
+        let item_attributes = Attributes {
+            size_from: None,
+            rest: false,
+            terminator: None,
+            ..attributes.clone()
+        };
         generate_for_item(
             &item_name,
             vec_generic,
             &quote! { # it_name },
             &quote! { #it_name . },
             false,
-            attributes,
+            &item_attributes,
             &mut item_size_code,
             &mut item_read_code,
             &mut item_write_code,
             context_val,
             prefixed_context_val,
+            &quote! { .in_index(index) #error_path },
+            mode,
         )?;
 
         // it_name may or may not be used by item_size_code
         size_code.extend(quote! {
-            #ty_size + #prefixed_field_dotted iter().fold(0, #[allow(unused_variables)] |n, #it_name| n + #item_size_code 0) +
+            #prefixed_field_dotted iter().for_each(#[allow(unused_variables)] |#it_name| { #item_size_code });
         });
+        if let Some(terminator) = &attributes.terminator {
+            size_code.extend(quote! {
+                {
+                    #[allow(unused_variables)]
+                    let #it_name: &#vec_generic = &(#terminator);
+                    #item_size_code
+                }
+            });
+        }
     }
-    let (plus_one, minus_one) = if attributes.size_is_next {
-        (quote! { + 1 }, quote! { - 1 })
-    } else {
-        (quote! {}, quote! {})
-    };
-    read_code.extend(quote! {
-        let mut buffer: [u8; #ty_size] = [0; #ty_size];
-        from.read_exact(&mut buffer)?;
-        _pos += #ty_size;
-        let mut size = #size_ty::#from_method(buffer) as usize #minus_one;
-    });
-    if attributes.byte_sized {
+    if attributes.rest || attributes.terminator.is_some() {
+        // nothing to write before the items
+    } else if attributes.byte_sized && !vec_u8 {
+        // items are sized at the position they will be written to
         write_code.extend(quote! {
-            let size = #prefixed_field_dotted iter().fold(0, #[allow(unused_variables)] |n, #it_name| n + #item_size_code 0);
-            let buffer: [u8; #ty_size] = (size as #size_ty #plus_one).#to_method();
-            to.write_all(&buffer)?;
-            _pos += #ty_size;
+            let size = {
+                let start = _pos + #prefix_size;
+                let mut _pos = start;
+                #prefixed_field_dotted iter().for_each(#[allow(unused_variables)] |#it_name| { #item_size_code });
+                _pos - start
+            };
         });
     } else {
         write_code.extend(quote! {
             let size = #prefixed_field_dotted len();
-            let buffer: [u8; #ty_size] = (size as #size_ty #plus_one).#to_method();
-            to.write_all(&buffer)?;
-            _pos += #ty_size;
         });
     }
+    match &ty_size {
+        _ if attributes.rest || attributes.terminator.is_some() => {}
+        Some(_) => {
+            read_code.extend(read_size(type_path, attributes, mode, error_path)?);
+            write_code.extend(write_size(type_path, attributes, mode, error_path)?);
+        }
+        None => size_from_code(attributes, read_code, write_code, error_path),
+    }
     // Vec<u8> can be read and written all at once
     if vec_u8 {
-        // byte size == count size for Vec<u8>
-        read_code.extend(quote! {
-            let mut #field_ident = vec![0_u8; size];
-            from.read_exact(&mut #field_ident)?;
-            _pos += size;
-        });
+        if attributes.rest {
+            let read_rest = mode.read_to_end(quote! { &mut #field_ident });
+            read_code.extend(quote! {
+                let mut #field_ident = plod::__private::Vec::new();
+                let size = #read_rest #map_vec_error;
+                _pos += size;
+            });
+            write_code.extend(quote! {
+                let size = #prefixed_field_dotted len();
+            });
+        } else {
+            // byte size == count size for Vec<u8>, the vec grows with the data read
+            read_code.extend(quote! {
+                let mut #field_ident = plod::__private::Vec::new();
+                if #read_vec #map_vec_error < size {
+                    return Err(plod::Error::new(plod::ErrorKind::UnexpectedEof).at(_pos) #error_path);
+                }
+                _pos += size;
+            });
+        }
         write_code.extend(quote! {
-            to.write_all(#prefixed_field_dotted as_slice())?;
+            #write_vec #map_vec_error;
             _pos += size;
         });
     } else {
-        if attributes.byte_sized {
+        if let Some(terminator) = &attributes.terminator {
+            // the terminator is read like an item but not stored
             read_code.extend(quote! {
-                let mut #field_ident = Vec::new();
-                while size > 0 {
+                let mut #field_ident = plod::__private::Vec::new();
+                let terminator: #vec_generic = #terminator;
+                let mut index = 0;
+                loop {
                     #item_read_code
-                    let #it_name = &#item_name;
-                    size -= #item_size_code 0;
-                    #field_ident.push(item);
+                    if #item_name == terminator {
+                        break;
+                    }
+                    index += 1;
+                    #field_ident.push(#item_name);
+                }
+            });
+        } else if attributes.byte_sized || attributes.rest {
+            // items are read from a slice that contains exactly their bytes, so that the last item
+            // can consume the rest of it, missing bytes are reported by the item that needs them
+            let data = if mode == Mode::Borrow {
+                if attributes.rest {
+                    quote! { let mut vec_data: &[u8] = ::core::mem::take(from); }
+                } else {
+                    quote! {
+                        let available = size.min(from.len());
+                        let mut vec_data = plod::__private::take(from, available) #map_vec_error;
+                    }
+                }
+            } else {
+                let read_data = if attributes.rest {
+                    mode.read_to_end(quote! { &mut vec_buffer })
+                } else {
+                    mode.read_up_to(quote! { &mut vec_buffer }, quote! { size })
+                };
+                quote! {
+                    let mut vec_buffer = plod::__private::Vec::new();
+                    let available = #read_data #map_vec_error;
+                    let mut vec_data = vec_buffer.as_slice();
+                }
+            };
+            let check_size = if attributes.rest {
+                TokenStream::new()
+            } else {
+                quote! {
+                    if available < size {
+                        return Err(plod::Error::new(plod::ErrorKind::UnexpectedEof).at(_pos) #error_path);
+                    }
+                }
+            };
+            read_code.extend(quote! {
+                let mut #field_ident = plod::__private::Vec::new();
+                {
+                    #data
+                    let from = &mut vec_data;
+                    let mut index = 0;
+                    while !from.is_empty() {
+                        #item_read_code
+                        index += 1;
+                        #field_ident.push(#item_name);
+                    }
+                    #check_size
                 }
             });
         } else {
             read_code.extend(quote! {
-                let mut #field_ident = Vec::new();
-                for _ in 0..size {
+                let mut #field_ident = plod::__private::Vec::new();
+                for index in 0..size {
                     #item_read_code
                     #field_ident.push(#item_name);
                 }
             });
         }
-        write_code.extend(quote! {
-            for #it_name in #prefixed_field_dotted iter() {
-                #item_write_code
-            }
-        });
+        if !is_vec {
+            read_code.extend(quote! {
+                let #field_ident: #type_path = #field_ident.into_iter().collect();
+            });
+        }
+        if let Some(terminator) = &attributes.terminator {
+            // an item equal to the terminator would end the collection early on read
+            let terminator_str = quote! { #terminator }.to_string();
+            // maps iterate over (&key, &value)
+            let is_map = type_path
+                .path
+                .segments
+                .last()
+                .is_some_and(|s| s.ident == "BTreeMap" || s.ident == "HashMap");
+            let terminator_ref = match is_map {
+                true => quote! { (&terminator.0, &terminator.1) },
+                false => quote! { &terminator },
+            };
+            write_code.extend(quote! {
+                let terminator: #vec_generic = #terminator;
+                for (index, #it_name) in #prefixed_field_dotted iter().enumerate() {
+                    if #it_name == #terminator_ref {
+                        return Err(plod::Error::new(plod::ErrorKind::InvalidValue {
+                            type_name: "terminated item",
+                            value: plod::__private::ToString::to_string(#terminator_str),
+                        }).at(_pos) .in_index(index) #error_path);
+                    }
+                    #item_write_code
+                }
+            });
+        } else {
+            write_code.extend(quote! {
+                for (index, #it_name) in #prefixed_field_dotted iter().enumerate() {
+                    #item_write_code
+                }
+            });
+        }
+        if attributes.terminator.is_some() {
+            write_code.extend(quote! {
+                {
+                    let index = #prefixed_field_dotted len();
+                    let #it_name: &#vec_generic = &terminator;
+                    #item_write_code
+                }
+            });
+        }
     }
     Ok(())
 }
@@ -0,0 +1,239 @@
+//! Asynchronous version of the `Plod` trait, for tokio style readers and writers
+
+use crate::{BigEndian, Error, ErrorKind, LittleEndian, Plod, PlodTagged, Result};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::ToString;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::future::Future;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The asynchronous plain old data trait, available with the `async` feature.
+///
+/// `#[derive(Plod)]` implements it with `#[plod(async)]`, with the same representation as `Plod`,
+/// so that data can be read directly from a socket without buffering a whole frame first.
+/// If you implement `Plod` manually for a type used within such a derived type, you must also
+/// implement `AsyncPlod` for it.
+pub trait AsyncPlod: Plod {
+    /// Read this structure from an asynchronous reader
+    fn read_from_async<R: AsyncRead + Unpin>(from: &mut R) -> impl Future<Output = Result<Self>>
+    where
+        Self::Context: Default,
+    {
+        async move {
+            let ctx = Self::Context::default();
+            Self::impl_read_from_async(from, &ctx, 0).await
+        }
+    }
+
+    /// Same as `read_from_async` with all parameters, you must implement this one.
+    /// See `Plod::impl_read_from`.
+    fn impl_read_from_async<R: AsyncRead + Unpin>(
+        from: &mut R,
+        ctx: &Self::Context,
+        pos: usize,
+    ) -> impl Future<Output = Result<Self>>;
+
+    /// Write this structure to an asynchronous writer
+    fn write_to_async<W: AsyncWrite + Unpin>(&self, to: &mut W) -> impl Future<Output = Result<()>>
+    where
+        Self::Context: Default,
+    {
+        async move {
+            let ctx = Self::Context::default();
+            self.impl_write_to_async(to, &ctx, 0).await
+        }
+    }
+
+    /// Same as `write_to_async` with all parameters, you must implement this one.
+    /// See `Plod::impl_write_to`.
+    fn impl_write_to_async<W: AsyncWrite + Unpin>(
+        &self,
+        to: &mut W,
+        ctx: &Self::Context,
+        pos: usize,
+    ) -> impl Future<Output = Result<()>>;
+}
+
+/// Asynchronous version of `PlodTagged`, available with the `async` feature
+pub trait AsyncPlodTagged: AsyncPlod + PlodTagged {
+    /// See `PlodTagged::impl_read_untagged`
+    fn impl_read_untagged_async<R: AsyncRead + Unpin>(
+        tag: Self::Tag,
+        from: &mut R,
+        ctx: &Self::Context,
+        pos: usize,
+    ) -> impl Future<Output = Result<Self>>;
+
+    /// See `PlodTagged::impl_write_untagged`
+    fn impl_write_untagged_async<W: AsyncWrite + Unpin>(
+        &self,
+        tag: Self::Tag,
+        to: &mut W,
+        ctx: &Self::Context,
+        pos: usize,
+    ) -> impl Future<Output = Result<()>>;
+}
+
+macro_rules! impl_primitive {
+    ($($ty:ident),*) => {
+        $(
+        impl_primitive!($ty, $ty, from_ne_bytes, to_ne_bytes, |x: $ty| x, |x: &$ty| *x);
+        impl_primitive!(BigEndian<$ty>, $ty, from_be_bytes, to_be_bytes, BigEndian, |x: &BigEndian<$ty>| x.0);
+        impl_primitive!(LittleEndian<$ty>, $ty, from_le_bytes, to_le_bytes, LittleEndian, |x: &LittleEndian<$ty>| x.0);
+        )*
+    };
+    ($self_ty:ty, $ty:ident, $from_method:ident, $to_method:ident, $wrap:expr, $unwrap:expr) => {
+        impl AsyncPlod for $self_ty {
+            async fn impl_read_from_async<R: AsyncRead + Unpin>(from: &mut R, _ctx: &(), pos: usize) -> Result<Self> {
+                let mut buffer = [0_u8; core::mem::size_of::<$ty>()];
+                from.read_exact(&mut buffer).await.map_err(|e| Error::from(e).at(pos))?;
+                Ok($wrap($ty::$from_method(buffer)))
+            }
+
+            async fn impl_write_to_async<W: AsyncWrite + Unpin>(&self, to: &mut W, _ctx: &(), pos: usize) -> Result<()> {
+                let buffer = $unwrap(self).$to_method();
+                to.write_all(&buffer).await.map_err(|e| Error::from(e).at(pos))
+            }
+        }
+    };
+}
+
+impl_primitive!(f32, f64, i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+impl AsyncPlod for bool {
+    async fn impl_read_from_async<R: AsyncRead + Unpin>(from: &mut R, ctx: &(), pos: usize) -> Result<Self> {
+        match u8::impl_read_from_async(from, ctx, pos).await? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(Error::new(ErrorKind::InvalidValue {
+                type_name: "bool",
+                value: value.to_string(),
+            })
+            .at(pos)),
+        }
+    }
+
+    async fn impl_write_to_async<W: AsyncWrite + Unpin>(&self, to: &mut W, ctx: &(), pos: usize) -> Result<()> {
+        (*self as u8).impl_write_to_async(to, ctx, pos).await
+    }
+}
+
+impl AsyncPlod for () {
+    async fn impl_read_from_async<R: AsyncRead + Unpin>(_from: &mut R, _ctx: &(), _pos: usize) -> Result<Self> {
+        Ok(())
+    }
+
+    async fn impl_write_to_async<W: AsyncWrite + Unpin>(&self, _to: &mut W, _ctx: &(), _pos: usize) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: AsyncPlod, const N: usize> AsyncPlod for [T; N] {
+    async fn impl_read_from_async<R: AsyncRead + Unpin>(from: &mut R, ctx: &Self::Context, mut pos: usize) -> Result<Self> {
+        let mut items = Vec::with_capacity(N);
+        for index in 0..N {
+            let item = T::impl_read_from_async(from, ctx, pos)
+                .await
+                .map_err(|e| e.at(pos).in_index(index))?;
+            pos += item.size_at_pos(pos);
+            items.push(item);
+        }
+        match items.try_into() {
+            Ok(array) => Ok(array),
+            Err(_) => unreachable!("exactly N items have been read"),
+        }
+    }
+
+    async fn impl_write_to_async<W: AsyncWrite + Unpin>(&self, to: &mut W, ctx: &Self::Context, mut pos: usize) -> Result<()> {
+        for (index, item) in self.iter().enumerate() {
+            item.impl_write_to_async(to, ctx, pos)
+                .await
+                .map_err(|e| e.at(pos).in_index(index))?;
+            pos += item.size_at_pos(pos);
+        }
+        Ok(())
+    }
+}
+
+// futures are boxed so that recursive types have a finite future size
+macro_rules! impl_pointer {
+    ($($(#[$attr:meta])* $ptr:ident),*) => {
+        $(
+        $(#[$attr])*
+        impl<T: AsyncPlod> AsyncPlod for $ptr<T> {
+            async fn impl_read_from_async<R: AsyncRead + Unpin>(from: &mut R, ctx: &Self::Context, pos: usize) -> Result<Self> {
+                Box::pin(T::impl_read_from_async(from, ctx, pos)).await.map($ptr::new)
+            }
+
+            async fn impl_write_to_async<W: AsyncWrite + Unpin>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()> {
+                Box::pin(T::impl_write_to_async(self, to, ctx, pos)).await
+            }
+        }
+        )*
+    };
+}
+
+impl_pointer!(Box, Rc, #[cfg(target_has_atomic = "ptr")] Arc);
+
+impl<T: AsyncPlod + Clone> AsyncPlod for Cow<'_, T> {
+    async fn impl_read_from_async<R: AsyncRead + Unpin>(from: &mut R, ctx: &Self::Context, pos: usize) -> Result<Self> {
+        T::impl_read_from_async(from, ctx, pos).await.map(Cow::Owned)
+    }
+
+    async fn impl_write_to_async<W: AsyncWrite + Unpin>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()> {
+        T::impl_write_to_async(self, to, ctx, pos).await
+    }
+}
+
+macro_rules! impl_tuple {
+    ($first:ident $first_index:tt $(, $ty:ident $index:tt)*) => {
+        impl<$first: AsyncPlod $(, $ty: AsyncPlod)*> AsyncPlod for ($first, $($ty,)*)
+        where
+            $(for<'a> &'a $ty::Context: From<&'a $first::Context>,)*
+        {
+            #[allow(non_snake_case)]
+            async fn impl_read_from_async<R: AsyncRead + Unpin>(from: &mut R, ctx: &Self::Context, mut pos: usize) -> Result<Self> {
+                let $first = $first::impl_read_from_async(from, ctx, pos)
+                    .await
+                    .map_err(|e| e.at(pos).in_field(stringify!($first_index)))?;
+                pos += $first.size_at_pos(pos);
+                $(
+                let $ty = $ty::impl_read_from_async(from, ctx.into(), pos)
+                    .await
+                    .map_err(|e| e.at(pos).in_field(stringify!($index)))?;
+                pos += $ty.size_at_pos(pos);
+                )*
+                let _ = pos;
+                Ok(($first, $($ty,)*))
+            }
+
+            async fn impl_write_to_async<W: AsyncWrite + Unpin>(&self, to: &mut W, ctx: &Self::Context, mut pos: usize) -> Result<()> {
+                self.$first_index.impl_write_to_async(to, ctx, pos)
+                    .await
+                    .map_err(|e| e.at(pos).in_field(stringify!($first_index)))?;
+                pos += self.$first_index.size_at_pos(pos);
+                $(
+                self.$index.impl_write_to_async(to, ctx.into(), pos)
+                    .await
+                    .map_err(|e| e.at(pos).in_field(stringify!($index)))?;
+                pos += self.$index.size_at_pos(pos);
+                )*
+                let _ = pos;
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_tuple!(A 0);
+impl_tuple!(A 0, B 1);
+impl_tuple!(A 0, B 1, C 2);
+impl_tuple!(A 0, B 1, C 2, D 3);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
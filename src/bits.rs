@@ -0,0 +1,122 @@
+//! Bitfield conversions used by generated code for `#[plod(bits=..)]` fields, all values are
+//! handled as `u128` so that storage units and field types can be mixed freely.
+
+use crate::{Error, ErrorKind, Result};
+use alloc::string::ToString;
+
+/// Storage unit of bitfields
+pub trait BitUnit {
+    /// Convert the storage unit to bits
+    fn to_bits(self) -> u128;
+    /// Convert bits to a storage unit, upper bits are known to be 0
+    fn from_bits(bits: u128) -> Self;
+}
+
+/// Type that can be stored in a bitfield
+pub trait Bitfield: Sized {
+    /// Extract a value of `bits` bits located at `shift` in `unit`
+    fn from_bits(unit: u128, shift: u32, bits: u32) -> Result<Self>;
+    /// Place this value in `bits` bits located at `shift`, fails if it doesn't fit
+    fn to_bits(&self, shift: u32, bits: u32) -> Result<u128>;
+}
+
+/// Mask of the `bits` lowest bits
+fn mask(bits: u32) -> u128 {
+    if bits >= u128::BITS {
+        u128::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+fn overflow<T: ToString>(value: &T, bits: u32) -> Error {
+    Error::new(ErrorKind::BitfieldOverflow {
+        bits,
+        value: value.to_string(),
+    })
+}
+
+macro_rules! impl_unsigned {
+    ($($ty:ident),*) => {
+        $(
+        impl BitUnit for $ty {
+            fn to_bits(self) -> u128 {
+                self as u128
+            }
+
+            fn from_bits(bits: u128) -> Self {
+                bits as $ty
+            }
+        }
+
+        impl Bitfield for $ty {
+            fn from_bits(unit: u128, shift: u32, bits: u32) -> Result<Self> {
+                Ok(((unit >> shift) & mask(bits)) as $ty)
+            }
+
+            fn to_bits(&self, shift: u32, bits: u32) -> Result<u128> {
+                let value = *self as u128;
+                if value & !mask(bits) != 0 {
+                    return Err(overflow(self, bits));
+                }
+                Ok(value << shift)
+            }
+        }
+        )*
+    };
+}
+
+impl_unsigned!(u8, u16, u32, u64, u128);
+
+macro_rules! impl_signed {
+    ($($ty:ident),*) => {
+        $(
+        impl BitUnit for $ty {
+            fn to_bits(self) -> u128 {
+                self as u128 & mask(<$ty>::BITS)
+            }
+
+            fn from_bits(bits: u128) -> Self {
+                bits as $ty
+            }
+        }
+
+        /// Signed values use two's complement and are sign extended on read
+        impl Bitfield for $ty {
+            fn from_bits(unit: u128, shift: u32, bits: u32) -> Result<Self> {
+                let value = ((unit >> shift) & mask(bits)) as i128;
+                let extension = i128::BITS - bits;
+                Ok(((value << extension) >> extension) as $ty)
+            }
+
+            fn to_bits(&self, shift: u32, bits: u32) -> Result<u128> {
+                let value = *self as i128;
+                let min = -1_i128 << (bits - 1);
+                if value < min || value > !min {
+                    return Err(overflow(self, bits));
+                }
+                Ok((value as u128 & mask(bits)) << shift)
+            }
+        }
+        )*
+    };
+}
+
+impl_signed!(i8, i16, i32, i64, i128);
+
+impl Bitfield for bool {
+    fn from_bits(unit: u128, shift: u32, bits: u32) -> Result<Self> {
+        match (unit >> shift) & mask(bits) {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(Error::new(ErrorKind::InvalidValue {
+                type_name: "bool",
+                value: value.to_string(),
+            })),
+        }
+    }
+
+    fn to_bits(&self, shift: u32, _bits: u32) -> Result<u128> {
+        Ok((*self as u128) << shift)
+    }
+}
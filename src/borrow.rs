@@ -0,0 +1,94 @@
+//! Zero-copy parsing from byte slices
+
+use crate::{Error, ErrorKind, PlodRead, PlodWrite, Result};
+use alloc::vec::Vec;
+
+/// Borrowed plain old data trait, implemented with `#[derive(Plod)]` and `#[plod(borrow)]`.
+///
+/// Types implementing it can have fields of type `&'a [u8]`, `&'a str` or `&'a [u8; N]` that point
+/// directly into the parsed data instead of copying it. Other fields are read with their `Plod`
+/// or `PlodBorrow` implementation.
+///
+/// ```
+/// use plod::{Plod, PlodBorrow};
+///
+/// #[derive(Plod)]
+/// #[plod(borrow)]
+/// struct Record<'a> {
+///     kind: u8,
+///     #[plod(size_type(u8))]
+///     payload: &'a [u8],
+/// }
+///
+/// let data = [1, 2, 10, 11, 12];
+/// let (record, rest) = Record::parse(&data).unwrap();
+/// assert_eq!(record.payload, &[10, 11]);
+/// assert_eq!(rest, &[12]);
+/// ```
+pub trait PlodBorrow<'a>: Sized {
+    /// Context passed to parse methods, see `Plod::Context`
+    type Context;
+
+    /// Parse this structure from the start of `input`, returns the rest of `input`
+    fn parse(input: &'a [u8]) -> Result<(Self, &'a [u8])>
+    where
+        Self::Context: Default,
+    {
+        Self::impl_parse(input, &Self::Context::default(), 0)
+    }
+
+    /// Same as `parse` with all parameters, you must implement this one.
+    /// `pos` is the position in bytes of `input` in the original data.
+    fn impl_parse(input: &'a [u8], ctx: &Self::Context, pos: usize) -> Result<(Self, &'a [u8])>;
+}
+
+/// Used by generated code to read from any reader, including slices
+#[doc(hidden)]
+pub fn read_exact<R: PlodRead + ?Sized>(from: &mut R, buf: &mut [u8]) -> Result<()> {
+    from.read_exact(buf)
+}
+
+/// Used by generated code to write to any writer, including vectors
+#[doc(hidden)]
+pub fn write_all<W: PlodWrite + ?Sized>(to: &mut W, buf: &[u8]) -> Result<()> {
+    to.write_all(buf)
+}
+
+/// Used by generated code to read everything left in a reader
+#[doc(hidden)]
+pub fn read_to_end<R: PlodRead + ?Sized>(from: &mut R, buf: &mut Vec<u8>) -> Result<usize> {
+    from.read_to_end(buf)
+}
+
+/// Used by generated code to read at most `size` bytes, it stops early at the end of data
+#[doc(hidden)]
+pub fn read_up_to<R: PlodRead + ?Sized>(from: &mut R, buf: &mut Vec<u8>, size: usize) -> Result<usize> {
+    let start = buf.len();
+    let mut chunk = [0_u8; 256];
+    while buf.len() - start < size {
+        let wanted = (size - (buf.len() - start)).min(chunk.len());
+        match from.read(&mut chunk[..wanted])? {
+            0 => break,
+            n => buf.extend_from_slice(&chunk[..n]),
+        }
+    }
+    Ok(buf.len() - start)
+}
+
+/// Used by generated code to borrow the first `size` bytes of a slice
+#[doc(hidden)]
+pub fn take<'a>(from: &mut &'a [u8], size: usize) -> Result<&'a [u8]> {
+    if size > from.len() {
+        return Err(Error::new(ErrorKind::UnexpectedEof));
+    }
+    let (data, rest) = from.split_at(size);
+    *from = rest;
+    Ok(data)
+}
+
+/// Used by generated code to borrow the first `size` bytes of a slice as a `str`
+#[doc(hidden)]
+pub fn take_str<'a>(from: &mut &'a [u8], size: usize) -> Result<&'a str> {
+    let data = take(from, size)?;
+    core::str::from_utf8(data).map_err(crate::string::utf8_error)
+}
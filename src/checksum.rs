@@ -0,0 +1,201 @@
+//! Checksums used by generated code for `#[plod(checksum=..)]` fields
+
+use crate::{Error, ErrorKind, Result};
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Checksum algorithm, chosen with `#[plod(checksum=..)]`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Checksum {
+    /// CRC-32 used by PNG, Ethernet and ZIP
+    Crc32,
+    /// CRC-32C (Castagnoli) used by iSCSI and SCTP
+    Crc32c,
+    /// CRC-16/ARC
+    Crc16Arc,
+    /// CRC-16/MODBUS
+    Crc16Modbus,
+    /// CRC-16/CCITT-FALSE
+    Crc16Ccitt,
+    /// CRC-16/XMODEM
+    Crc16Xmodem,
+    /// One's complement sum of 16 bits words used by IPv4, TCP and UDP
+    Internet,
+    /// Adler-32 used by zlib
+    Adler32,
+}
+
+/// Table for a CRC computed from the least significant bit, `poly` is reversed
+const fn reflected_table(poly: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ poly } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Table for a 16 bits CRC computed from the most significant bit
+const fn normal_table16(poly: u16) -> [u16; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ poly } else { crc << 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+const CRC32_TABLE: [u32; 256] = reflected_table(0xedb8_8320);
+const CRC32C_TABLE: [u32; 256] = reflected_table(0x82f6_3b78);
+const CRC16_REFLECTED_TABLE: [u32; 256] = reflected_table(0xa001);
+const CRC16_NORMAL_TABLE: [u16; 256] = normal_table16(0x1021);
+
+fn reflected(bytes: impl Iterator<Item = u8>, table: &[u32; 256], init: u32) -> u32 {
+    bytes.fold(init, |crc, b| table[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+fn normal16(bytes: impl Iterator<Item = u8>, init: u16) -> u16 {
+    bytes.fold(init, |crc, b| CRC16_NORMAL_TABLE[((crc >> 8) ^ b as u16) as usize] ^ (crc << 8))
+}
+
+fn internet(bytes: impl Iterator<Item = u8>) -> u16 {
+    let mut sum = 0_u32;
+    let mut high = None;
+    for b in bytes {
+        match high.take() {
+            None => high = Some(b),
+            Some(h) => sum += u16::from_be_bytes([h, b]) as u32,
+        }
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    // an odd number of bytes is padded with a zero
+    if let Some(h) = high {
+        sum += u16::from_be_bytes([h, 0]) as u32;
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn adler32(bytes: impl Iterator<Item = u8>) -> u32 {
+    let (a, b) = bytes.fold((1_u32, 0_u32), |(a, b), byte| {
+        let a = (a + byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+impl Checksum {
+    fn name(self) -> &'static str {
+        match self {
+            Checksum::Crc32 => "crc32",
+            Checksum::Crc32c => "crc32c",
+            Checksum::Crc16Arc => "crc16_arc",
+            Checksum::Crc16Modbus => "crc16_modbus",
+            Checksum::Crc16Ccitt => "crc16_ccitt",
+            Checksum::Crc16Xmodem => "crc16_xmodem",
+            Checksum::Internet => "internet",
+            Checksum::Adler32 => "adler32",
+        }
+    }
+
+    /// Checksum of `data`, bytes in the `zeroed` range count as zeros, it is where an embedded
+    /// checksum is stored
+    pub fn compute(self, data: &[u8], zeroed: Option<Range<usize>>) -> u32 {
+        let bytes = data.iter().enumerate().map(|(i, b)| match &zeroed {
+            Some(range) if range.contains(&i) => 0,
+            _ => *b,
+        });
+        match self {
+            Checksum::Crc32 => !reflected(bytes, &CRC32_TABLE, !0),
+            Checksum::Crc32c => !reflected(bytes, &CRC32C_TABLE, !0),
+            Checksum::Crc16Arc => reflected(bytes, &CRC16_REFLECTED_TABLE, 0),
+            Checksum::Crc16Modbus => reflected(bytes, &CRC16_REFLECTED_TABLE, 0xffff),
+            Checksum::Crc16Ccitt => normal16(bytes, 0xffff) as u32,
+            Checksum::Crc16Xmodem => normal16(bytes, 0) as u32,
+            Checksum::Internet => internet(bytes) as u32,
+            Checksum::Adler32 => adler32(bytes),
+        }
+    }
+
+    /// Check the value read against the value computed
+    pub fn check<T: PartialEq + ToString>(self, found: &T, expected: &T) -> Result<()> {
+        if found != expected {
+            return Err(Error::new(ErrorKind::BadChecksum {
+                algorithm: self.name(),
+                expected: expected.to_string(),
+                found: found.to_string(),
+            }));
+        }
+        Ok(())
+    }
+}
+
+/// Integer types that can store a checksum, larger checksums are truncated
+pub trait ChecksumField {
+    /// Convert a computed checksum
+    fn from_checksum(value: u32) -> Self;
+}
+
+macro_rules! impl_checksum_field {
+    ($($ty:ty),*) => {
+        $(
+        impl ChecksumField for $ty {
+            fn from_checksum(value: u32) -> Self {
+                value as $ty
+            }
+        }
+        )*
+    };
+}
+impl_checksum_field!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+/// Reader that keeps a copy of everything read, to compute a checksum
+pub struct Recorder<'a, R: ?Sized> {
+    inner: &'a mut R,
+    data: &'a mut Vec<u8>,
+}
+
+impl<'a, R: ?Sized> Recorder<'a, R> {
+    /// Record bytes read from `inner` into `data`
+    pub fn new(inner: &'a mut R, data: &'a mut Vec<u8>) -> Self {
+        Recorder { inner, data }
+    }
+}
+
+impl<R: crate::PlodRead + ?Sized> crate::PlodRead for Recorder<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.data.extend_from_slice(&buf[..size]);
+        Ok(size)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<R: tokio::io::AsyncRead + Unpin + ?Sized> tokio::io::AsyncRead for Recorder<'_, R> {
+    fn poll_read(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> core::task::Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let start = buf.filled().len();
+        core::task::ready!(core::pin::Pin::new(&mut *this.inner).poll_read(cx, buf))?;
+        this.data.extend_from_slice(&buf.filled()[start..]);
+        core::task::Poll::Ready(Ok(()))
+    }
+}
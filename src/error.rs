@@ -0,0 +1,326 @@
+//! Error type returned by all plod operations
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// What went wrong while reading or writing plain old data
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The reader ended before the data structure was complete
+    UnexpectedEof,
+    /// An enum discriminant didn't match any variant
+    UnknownTag {
+        /// Name of the enum
+        type_name: &'static str,
+        /// Value of the discriminant found
+        value: String,
+    },
+    /// A magic value was not found where it was expected
+    BadMagic {
+        /// Value defined by `#[plod(magic(..))]`
+        expected: String,
+        /// Value found at rest
+        found: String,
+    },
+    /// A checksum read doesn't match the data, with `#[plod(checksum=..)]`
+    BadChecksum {
+        /// Name of the checksum algorithm
+        algorithm: &'static str,
+        /// Value computed from the data
+        expected: String,
+        /// Value found at rest
+        found: String,
+    },
+    /// A value read is not valid for its type, eg: a bool that is neither 0 nor 1
+    InvalidValue {
+        /// Name of the type
+        type_name: &'static str,
+        /// Value found at rest
+        value: String,
+    },
+    /// A padding byte doesn't have the `#[plod(fill=..)]` value, with `#[plod(check_padding)]`
+    BadPadding {
+        /// Value defined by `#[plod(fill=..)]`
+        expected: u8,
+        /// Value found at rest
+        found: u8,
+    },
+    /// A value doesn't fit in its `#[plod(bits=..)]` bitfield
+    BitfieldOverflow {
+        /// Size of the bitfield
+        bits: u32,
+        /// Value that was written
+        value: String,
+    },
+    /// A `Vec` size doesn't match the field it is taken from on write
+    SizeMismatch {
+        /// Name of the field that contains the size
+        size_field: &'static str,
+        /// Value of this field
+        expected: usize,
+        /// Actual size of the `Vec`
+        found: usize,
+    },
+    /// A string is not valid UTF-8
+    InvalidUtf8 {
+        /// Number of valid bytes at the start of the string
+        valid_up_to: usize,
+    },
+    /// A string cannot be decoded or encoded with its `#[plod(encoding=..)]`
+    InvalidEncoding {
+        /// Name of the encoding
+        encoding: &'static str,
+        /// Position of the first invalid byte on read, or of the first invalid character on write
+        position: usize,
+    },
+    /// A value is longer than its `#[plod(fixed=..)]` size
+    TooLong {
+        /// Size available
+        size: usize,
+        /// Actual size of the value
+        found: usize,
+    },
+    /// An `Option` doesn't match its `#[plod(if=..)]` condition on write
+    OptionMismatch {
+        /// Value of the condition
+        condition: bool,
+    },
+    /// A field cannot be converted to its `#[plod(wire=..)]` type on write, with `try_into`
+    InvalidConversion {
+        /// Type of the field
+        from: &'static str,
+        /// Type stored
+        to: &'static str,
+    },
+    /// A `#[plod(tag_from=..)]` field doesn't select the variant being written, or doesn't match
+    /// the tag it retains
+    TagMismatch {
+        /// Name of the enum
+        type_name: &'static str,
+        /// Name of the variant being written
+        variant: &'static str,
+        /// Value of the tag field
+        value: String,
+    },
+    /// A `#[plod(skip)]` variant cannot be written
+    SkippedVariant {
+        /// Name of the variant
+        variant: &'static str,
+    },
+    /// The writer could not accept all the data
+    WriteZero,
+    /// Any other error reported by the reader or the writer
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnexpectedEof => write!(f, "unexpected end of data"),
+            ErrorKind::UnknownTag { type_name, value } => {
+                write!(f, "tag value {} not found for {}", value, type_name)
+            }
+            ErrorKind::BadMagic { expected, found } => {
+                write!(f, "magic value {} expected, found {}", expected, found)
+            }
+            ErrorKind::BadChecksum { algorithm, expected, found } => {
+                write!(f, "{} checksum {} expected, found {}", algorithm, expected, found)
+            }
+            ErrorKind::InvalidValue { type_name, value } => {
+                write!(f, "invalid value {} for {}", value, type_name)
+            }
+            ErrorKind::BadPadding { expected, found } => {
+                write!(f, "padding byte {} expected, found {}", expected, found)
+            }
+            ErrorKind::BitfieldOverflow { bits, value } => {
+                write!(f, "value {} does not fit in {} bits", value, bits)
+            }
+            ErrorKind::SizeMismatch { size_field, expected, found } => {
+                write!(f, "size {} does not match {} = {}", found, size_field, expected)
+            }
+            ErrorKind::InvalidUtf8 { valid_up_to } => {
+                write!(f, "invalid utf-8 string after {} valid bytes", valid_up_to)
+            }
+            ErrorKind::InvalidEncoding { encoding, position } => {
+                write!(f, "invalid {} string at position {}", encoding, position)
+            }
+            ErrorKind::TooLong { size, found } => {
+                write!(f, "size {} is larger than the fixed size {}", found, size)
+            }
+            ErrorKind::OptionMismatch { condition } => {
+                let option = if *condition { "None" } else { "Some" };
+                write!(f, "option is {} but its condition is {}", option, condition)
+            }
+            ErrorKind::InvalidConversion { from, to } => {
+                write!(f, "value of type {} cannot be converted to {}", from, to)
+            }
+            ErrorKind::TagMismatch { type_name, variant, value } => {
+                write!(f, "tag value {} does not select variant {} of {}", value, variant, type_name)
+            }
+            ErrorKind::SkippedVariant { variant } => {
+                write!(f, "variant {} cannot be written because it is plod(skip)", variant)
+            }
+            ErrorKind::WriteZero => write!(f, "failed to write whole buffer"),
+            #[cfg(feature = "std")]
+            ErrorKind::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// One step in the path leading to the item that failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathItem {
+    /// Field of a struct or of a variant (the index for tuple fields)
+    Field(&'static str),
+    /// Enum variant
+    Variant(&'static str),
+    /// Index in an array or a collection
+    Index(usize),
+}
+
+/// Plod error, it tells what went wrong, where in the data and in which field.
+///
+/// The path is built from the outermost type, eg: `TestStruct3.c.D[2].B.val`, and the offset
+/// is the position in bytes, relative to the start of the outermost `read_from` or `write_to`.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    offset: Option<usize>,
+    type_name: Option<&'static str>,
+    path: Vec<PathItem>,
+}
+
+impl Error {
+    /// Create an error without any location information
+    pub fn new(kind: ErrorKind) -> Self {
+        Error {
+            kind,
+            offset: None,
+            type_name: None,
+            path: Vec::new(),
+        }
+    }
+
+    /// Kind of error
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Consume the error to get its kind
+    pub fn into_kind(self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Byte offset of the item that failed, if known
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// Path items from the outermost type to the item that failed
+    pub fn path_items(&self) -> &[PathItem] {
+        &self.path
+    }
+
+    /// Printable path of the item that failed, eg: `TestStruct3.c.D[2].B.val`
+    pub fn path(&self) -> String {
+        let mut result = String::from(self.type_name.unwrap_or(""));
+        for item in self.path.iter() {
+            match item {
+                PathItem::Field(name) | PathItem::Variant(name) => {
+                    result.push('.');
+                    result.push_str(name);
+                }
+                PathItem::Index(i) => result.push_str(&format!("[{}]", i)),
+            }
+        }
+        result
+    }
+
+    /// Set the offset if it is not already known, the innermost offset is the most accurate
+    pub fn at(mut self, offset: usize) -> Self {
+        if self.offset.is_none() {
+            self.offset = Some(offset);
+        }
+        self
+    }
+
+    /// Prepend a field name to the path
+    pub fn in_field(mut self, name: &'static str) -> Self {
+        self.path.insert(0, PathItem::Field(name));
+        self
+    }
+
+    /// Prepend a variant name to the path
+    pub fn in_variant(mut self, name: &'static str) -> Self {
+        self.path.insert(0, PathItem::Variant(name));
+        self
+    }
+
+    /// Prepend a collection index to the path
+    pub fn in_index(mut self, index: usize) -> Self {
+        self.path.insert(0, PathItem::Index(index));
+        self
+    }
+
+    /// Set the type the path starts from, the outermost type wins
+    pub fn in_type(mut self, name: &'static str) -> Self {
+        self.type_name = Some(name);
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        if self.type_name.is_some() || !self.path.is_empty() {
+            write!(f, " in {}", self.path())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error::new(kind)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => Error::new(ErrorKind::UnexpectedEof),
+            std::io::ErrorKind::WriteZero => Error::new(ErrorKind::WriteZero),
+            _ => Error::new(ErrorKind::Io(e)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(e: Error) -> Self {
+        match e.kind {
+            ErrorKind::Io(io) => io,
+            ErrorKind::UnexpectedEof => std::io::Error::new(std::io::ErrorKind::UnexpectedEof, e),
+            ErrorKind::WriteZero => std::io::Error::new(std::io::ErrorKind::WriteZero, e),
+            _ => std::io::Error::other(e),
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! Plod implementations for standard types, they are used by manually implemented `Plod` and by
+//! generic types, the derive has its own inlined implementation for primitives.
+
+use crate::{Error, ErrorKind, Plod, PlodRead, PlodWrite, Result};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::ToString;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Wrapper to read and write a primitive in big endian
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BigEndian<T>(pub T);
+
+/// Wrapper to read and write a primitive in little endian
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LittleEndian<T>(pub T);
+
+/// Byte order of a `#[plod(endian = dynamic)]` type, it is chosen at runtime.
+///
+/// It is obtained from the context with `ByteOrder::from(ctx)`, so it can be the context itself,
+/// and `()` gives the native byte order. A magic value of such type can be found in either byte
+/// order, it then sets the byte order of the next fields, which get it as their context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    /// Most significant byte first
+    Big,
+    /// Least significant byte first
+    Little,
+}
+
+impl ByteOrder {
+    /// Byte order of the target
+    pub const NATIVE: ByteOrder = if cfg!(target_endian = "big") {
+        ByteOrder::Big
+    } else {
+        ByteOrder::Little
+    };
+
+    /// The other byte order
+    pub fn swapped(self) -> Self {
+        match self {
+            ByteOrder::Big => ByteOrder::Little,
+            ByteOrder::Little => ByteOrder::Big,
+        }
+    }
+}
+
+impl Default for ByteOrder {
+    fn default() -> Self {
+        ByteOrder::NATIVE
+    }
+}
+
+impl From<&ByteOrder> for ByteOrder {
+    fn from(byte_order: &ByteOrder) -> Self {
+        *byte_order
+    }
+}
+
+impl From<&()> for ByteOrder {
+    fn from(_: &()) -> Self {
+        ByteOrder::NATIVE
+    }
+}
+
+/// Fields that don't need a byte order can be found in a dynamic type
+impl From<&ByteOrder> for &() {
+    fn from(_: &ByteOrder) -> Self {
+        &()
+    }
+}
+
+macro_rules! impl_primitive {
+    ($($ty:ident),*) => {
+        $(
+        impl_primitive!($ty, $ty, from_ne_bytes, to_ne_bytes, |x: $ty| x, |x: &$ty| *x);
+        impl_primitive!(BigEndian<$ty>, $ty, from_be_bytes, to_be_bytes, BigEndian, |x: &BigEndian<$ty>| x.0);
+        impl_primitive!(LittleEndian<$ty>, $ty, from_le_bytes, to_le_bytes, LittleEndian, |x: &LittleEndian<$ty>| x.0);
+        )*
+    };
+    ($self_ty:ty, $ty:ident, $from_method:ident, $to_method:ident, $wrap:expr, $unwrap:expr) => {
+        impl Plod for $self_ty {
+            type Context = ();
+
+            const ALIGNMENT: usize = core::mem::align_of::<$ty>();
+
+            fn size_at_rest(&self) -> usize {
+                core::mem::size_of::<$ty>()
+            }
+
+            fn impl_read_from<R: PlodRead>(from: &mut R, _ctx: &(), pos: usize) -> Result<Self> {
+                let mut buffer = [0_u8; core::mem::size_of::<$ty>()];
+                from.read_exact(&mut buffer).map_err(|e| Error::from(e).at(pos))?;
+                Ok($wrap($ty::$from_method(buffer)))
+            }
+
+            fn impl_write_to<W: PlodWrite>(&self, to: &mut W, _ctx: &(), pos: usize) -> Result<()> {
+                let buffer = $unwrap(self).$to_method();
+                to.write_all(&buffer).map_err(|e| Error::from(e).at(pos))
+            }
+        }
+    };
+}
+
+impl_primitive!(f32, f64, i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+/// A bool is stored as a byte, 0 is false and 1 is true, any other value is an error
+impl Plod for bool {
+    type Context = ();
+
+    fn size_at_rest(&self) -> usize {
+        1
+    }
+
+    fn impl_read_from<R: PlodRead>(from: &mut R, ctx: &(), pos: usize) -> Result<Self> {
+        match u8::impl_read_from(from, ctx, pos)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(Error::new(ErrorKind::InvalidValue {
+                type_name: "bool",
+                value: value.to_string(),
+            })
+            .at(pos)),
+        }
+    }
+
+    fn impl_write_to<W: PlodWrite>(&self, to: &mut W, ctx: &(), pos: usize) -> Result<()> {
+        (*self as u8).impl_write_to(to, ctx, pos)
+    }
+}
+
+impl Plod for () {
+    type Context = ();
+
+    fn size_at_rest(&self) -> usize {
+        0
+    }
+
+    fn impl_read_from<R: PlodRead>(_from: &mut R, _ctx: &(), _pos: usize) -> Result<Self> {
+        Ok(())
+    }
+
+    fn impl_write_to<W: PlodWrite>(&self, _to: &mut W, _ctx: &(), _pos: usize) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Arrays pass their context to each item
+impl<T: Plod, const N: usize> Plod for [T; N] {
+    type Context = T::Context;
+
+    const ALIGNMENT: usize = T::ALIGNMENT;
+
+    fn size_at_rest(&self) -> usize {
+        self.size_at_pos(0)
+    }
+
+    fn size_at_pos(&self, pos: usize) -> usize {
+        self.iter().fold(pos, |pos, item| pos + item.size_at_pos(pos)) - pos
+    }
+
+    fn impl_read_from<R: PlodRead>(from: &mut R, ctx: &Self::Context, mut pos: usize) -> Result<Self> {
+        let mut items = Vec::with_capacity(N);
+        for index in 0..N {
+            let item = T::impl_read_from(from, ctx, pos).map_err(|e| e.at(pos).in_index(index))?;
+            pos += item.size_at_pos(pos);
+            items.push(item);
+        }
+        match items.try_into() {
+            Ok(array) => Ok(array),
+            Err(_) => unreachable!("exactly N items have been read"),
+        }
+    }
+
+    fn impl_write_to<W: PlodWrite>(&self, to: &mut W, ctx: &Self::Context, mut pos: usize) -> Result<()> {
+        for (index, item) in self.iter().enumerate() {
+            item.impl_write_to(to, ctx, pos).map_err(|e| e.at(pos).in_index(index))?;
+            pos += item.size_at_pos(pos);
+        }
+        Ok(())
+    }
+}
+
+/// Smart pointers are stored like the value they point to, `Box` makes recursive types possible
+macro_rules! impl_pointer {
+    ($($(#[$attr:meta])* $ptr:ident),*) => {
+        $(
+        $(#[$attr])*
+        impl<T: Plod> Plod for $ptr<T> {
+            type Context = T::Context;
+
+            const ALIGNMENT: usize = T::ALIGNMENT;
+
+            fn size_at_rest(&self) -> usize {
+                T::size_at_rest(self)
+            }
+
+            fn size_at_pos(&self, pos: usize) -> usize {
+                T::size_at_pos(self, pos)
+            }
+
+            fn impl_read_from<R: PlodRead>(from: &mut R, ctx: &Self::Context, pos: usize) -> Result<Self> {
+                T::impl_read_from(from, ctx, pos).map($ptr::new)
+            }
+
+            fn impl_write_to<W: PlodWrite>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()> {
+                T::impl_write_to(self, to, ctx, pos)
+            }
+        }
+        )*
+    };
+}
+
+impl_pointer!(Box, Rc, #[cfg(target_has_atomic = "ptr")] Arc);
+
+/// A `Cow` is stored like the value it contains, it is always read as `Cow::Owned`
+impl<T: Plod + Clone> Plod for Cow<'_, T> {
+    type Context = T::Context;
+
+    const ALIGNMENT: usize = T::ALIGNMENT;
+
+    fn size_at_rest(&self) -> usize {
+        T::size_at_rest(self)
+    }
+
+    fn size_at_pos(&self, pos: usize) -> usize {
+        T::size_at_pos(self, pos)
+    }
+
+    fn impl_read_from<R: PlodRead>(from: &mut R, ctx: &Self::Context, pos: usize) -> Result<Self> {
+        T::impl_read_from(from, ctx, pos).map(Cow::Owned)
+    }
+
+    fn impl_write_to<W: PlodWrite>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()> {
+        T::impl_write_to(self, to, ctx, pos)
+    }
+}
+
+/// Tuples use the context of their first item, other items get it with `into()`
+macro_rules! impl_tuple {
+    ($first:ident $first_index:tt $(, $ty:ident $index:tt)*) => {
+        impl<$first: Plod $(, $ty: Plod)*> Plod for ($first, $($ty,)*)
+        where
+            $(for<'a> &'a $ty::Context: From<&'a $first::Context>,)*
+        {
+            type Context = $first::Context;
+
+            const ALIGNMENT: usize = {
+                let align = $first::ALIGNMENT;
+                $(let align = if $ty::ALIGNMENT > align { $ty::ALIGNMENT } else { align };)*
+                align
+            };
+
+            fn size_at_rest(&self) -> usize {
+                self.size_at_pos(0)
+            }
+
+            fn size_at_pos(&self, pos: usize) -> usize {
+                let end = pos + self.$first_index.size_at_pos(pos);
+                $(let end = end + self.$index.size_at_pos(end);)*
+                end - pos
+            }
+
+            #[allow(non_snake_case)]
+            fn impl_read_from<R: PlodRead>(from: &mut R, ctx: &Self::Context, mut pos: usize) -> Result<Self> {
+                let $first = $first::impl_read_from(from, ctx, pos)
+                    .map_err(|e| e.at(pos).in_field(stringify!($first_index)))?;
+                pos += $first.size_at_pos(pos);
+                $(
+                let $ty = $ty::impl_read_from(from, ctx.into(), pos)
+                    .map_err(|e| e.at(pos).in_field(stringify!($index)))?;
+                pos += $ty.size_at_pos(pos);
+                )*
+                let _ = pos;
+                Ok(($first, $($ty,)*))
+            }
+
+            fn impl_write_to<W: PlodWrite>(&self, to: &mut W, ctx: &Self::Context, mut pos: usize) -> Result<()> {
+                self.$first_index.impl_write_to(to, ctx, pos)
+                    .map_err(|e| e.at(pos).in_field(stringify!($first_index)))?;
+                pos += self.$first_index.size_at_pos(pos);
+                $(
+                self.$index.impl_write_to(to, ctx.into(), pos)
+                    .map_err(|e| e.at(pos).in_field(stringify!($index)))?;
+                pos += self.$index.size_at_pos(pos);
+                )*
+                let _ = pos;
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_tuple!(A 0);
+impl_tuple!(A 0, B 1);
+impl_tuple!(A 0, B 1, C 2);
+impl_tuple!(A 0, B 1, C 2, D 3);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
@@ -0,0 +1,154 @@
+//! Reader and writer traits used by `Plod`.
+//!
+//! They are plod-owned traits with the same methods with or without the `std` feature, so that a
+//! reader or a writer implemented for one configuration works in the other. They are implemented
+//! for byte slices and `Vec<u8>`, and with `std`, for anything that implements `std::io::Read` or
+//! `std::io::Write`.
+
+use crate::{Error, ErrorKind, Result};
+use alloc::vec::Vec;
+
+/// Source of bytes for `Plod::impl_read_from`
+pub trait PlodRead {
+    /// Read some bytes into `buf`, returns the number of bytes read, 0 means end of data
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Read exactly enough bytes to fill `buf`, fails with `ErrorKind::UnexpectedEof` otherwise
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(Error::new(ErrorKind::UnexpectedEof)),
+                n => buf = &mut buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Read all bytes until the end of data and append them to `buf`, returns their number
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        let start = buf.len();
+        let mut chunk = [0_u8; 256];
+        loop {
+            match self.read(&mut chunk)? {
+                0 => return Ok(buf.len() - start),
+                n => buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+}
+
+/// Destination of bytes for `Plod::impl_write_to`
+pub trait PlodWrite {
+    /// Write all bytes from `buf`, fails with `ErrorKind::WriteZero` if there is no more room
+    fn write_all(&mut self, buf: &[u8]) -> Result<()>;
+}
+
+#[cfg(feature = "std")]
+mod with_std {
+    use super::{PlodRead, PlodWrite};
+    use crate::Result;
+    use alloc::vec::Vec;
+
+    /// Any `std::io::Read` is a `PlodRead`, interrupted reads are retried
+    impl<R: std::io::Read + ?Sized> PlodRead for R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            loop {
+                match std::io::Read::read(self, buf) {
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    result => return Ok(result?),
+                }
+            }
+        }
+
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+            Ok(std::io::Read::read_exact(self, buf)?)
+        }
+
+        fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+            Ok(std::io::Read::read_to_end(self, buf)?)
+        }
+    }
+
+    /// Any `std::io::Write` is a `PlodWrite`
+    impl<W: std::io::Write + ?Sized> PlodWrite for W {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            Ok(std::io::Write::write_all(self, buf)?)
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod without_std {
+    use super::{PlodRead, PlodWrite};
+    use crate::{Error, ErrorKind, Result};
+    use alloc::vec::Vec;
+
+    /// Reading from a slice consumes it
+    impl PlodRead for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let size = buf.len().min(self.len());
+            let (data, rest) = self.split_at(size);
+            buf[..size].copy_from_slice(data);
+            *self = rest;
+            Ok(size)
+        }
+
+        fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+            let size = self.len();
+            buf.extend_from_slice(self);
+            *self = &self[size..];
+            Ok(size)
+        }
+
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+            if buf.len() > self.len() {
+                *self = &self[self.len()..];
+                return Err(Error::new(ErrorKind::UnexpectedEof));
+            }
+            let (data, rest) = self.split_at(buf.len());
+            buf.copy_from_slice(data);
+            *self = rest;
+            Ok(())
+        }
+    }
+
+    impl<R: PlodRead + ?Sized> PlodRead for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+            (**self).read_exact(buf)
+        }
+
+        fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+            (**self).read_to_end(buf)
+        }
+    }
+
+    /// Writing to a slice consumes it
+    impl PlodWrite for &mut [u8] {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            if buf.len() > self.len() {
+                return Err(Error::new(ErrorKind::WriteZero));
+            }
+            let (data, rest) = core::mem::take(self).split_at_mut(buf.len());
+            data.copy_from_slice(buf);
+            *self = rest;
+            Ok(())
+        }
+    }
+
+    impl PlodWrite for Vec<u8> {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            self.extend_from_slice(buf);
+            Ok(())
+        }
+    }
+
+    impl<W: PlodWrite + ?Sized> PlodWrite for &mut W {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            (**self).write_all(buf)
+        }
+    }
+}
@@ -9,6 +9,7 @@
 //!
 //! Since it uses the standard `Read` and `Write` traits, Plod can be used to read and write
 //! binary files as well as network protocols as long as you have a reader or a writer.
+//! Plod also works in `no_std` environments, see [`no_std`](#no_std).
 //! The `Plod` trait also have access to a context and position to allow for more cases where
 //! automatic derive is possible.
 //!
@@ -52,20 +53,82 @@
 //! Plod is for *plain old data*, which means that is well suited for known, existing, binary formats.
 //! *But*:
 //! - If you want a way to serialize *your own* data and be able to read it later, you should
-//! prefer serde which can serialize any data into many more formats that can be self describing.
+//!   prefer serde which can serialize any data into many more formats that can be self describing.
 //!
 //! - If your file format is *not binary* and not easily supported by serde, you may look at
-//! [`nom`] for parsing it.
+//!   [`nom`] for parsing it.
 //!
 //! - If your data only contains primary types and all you want is speed you may take a look at
-//! [`plain`], [`pod`] and [`nue`].
+//!   [`plain`], [`pod`] and [`nue`].
 //!
 //! # Special cases
 //!
 //! Plod use the obvious representation for struct as C does. However, some data structure are not so obvious.
 //! - `enum` are represented with a specific tag at the start, each variant can have its own size
+//!   With `#[plod(tag_from=<field>)]`, the tag is an earlier field of the enclosing struct instead.
+//!   A `#[plod(other)]` variant keeps unknown tags and their raw bytes.
 //! - `Vec` are represented with their size at the start (either in bytes or in item count)
+//! - `VecDeque`, `BTreeSet`, `HashSet`, `Box<[T]>` and maps are represented like a `Vec`, map entries
+//!   are `(key, value)` tuples.
+//! - `String` are represented like a `Vec<u8>`, with `#[plod(cstr)]` as a null terminated string or
+//!   with `#[plod(fixed=N)]` as a string padded to `N` bytes.
 //! - Skipped values are not represented, they are ignored when written and replaced with `default()`when read.
+//! - `bool` is represented as a byte, 0 or 1.
+//! - With `#[plod(endian = dynamic)]`, the byte order is a [`ByteOrder`] taken from the context, or
+//!   detected from a byte swapped `magic(..)` value, like pcap files.
+//! - Checksums are declared with `#[plod(checksum=crc32)]` on an integer field, they are computed on
+//!   write and verified on read, over previous fields or over `from=<field>` to `to=<field>`.
+//! - Header fields that describe a later field are declared with `#[plod(len_of=payload)]`,
+//!   `#[plod(count_of=items)]` or `#[plod(size_of_self)]`, they are computed on write and checked
+//!   on read.
+//! - A field whose type differs from the type stored is converted with `#[plod(wire=u8)]`, using
+//!   `From` and `Into`, or `TryFrom` and `TryInto` with `try_from` and `try_into`.
+//! - A field that needs special handling uses its own functions with `#[plod(with=module)]`, or
+//!   with `#[plod(read_with=..)]`, `#[plod(write_with=..)]` and `#[plod(size_with=..)]`.
+//!
+//! # Implementing Plod manually
+//!
+//! Primitive types, `bool`, `()`, arrays, tuples and smart pointers (`Box`, `Rc`, `Arc` and `Cow`)
+//! implement `Plod`, so a manual implementation can delegate to them, and a generic type can be
+//! bounded by `T: Plod`. Smart pointers are stored like the value they point to, so that recursive
+//! types can be derived with `Box`.
+//! Since the endianness is implied by the implementation, primitive types are read and written
+//! in native endianness, wrap them into [`BigEndian`] or [`LittleEndian`] to choose another one.
+//! ```
+//! use plod::{BigEndian, Plod};
+//!
+//! let value = BigEndian(0x1234_u16);
+//! let mut memory: Vec<u8> = Vec::new();
+//! value.write_to(&mut memory).unwrap();
+//! assert_eq!(memory, [0x12, 0x34]);
+//! ```
+//!
+//! # Zero-copy parsing
+//!
+//! With `#[plod(borrow)]`, the derive implements [`PlodBorrow`] instead of `Plod`, so that fields
+//! of type `&'a [u8]`, `&'a str` or `&'a [u8; N]` are parsed without copy from a byte slice, like
+//! a memory mapped file.
+//!
+//! # Errors
+//!
+//! All methods return a [`plod::Error`](Error). Its [`kind`](Error::kind) tells what went wrong,
+//! its [`offset`](Error::offset) tells at which byte and its [`path`](Error::path) tells in which
+//! field, eg: `TestStruct3.c.D[2].B.val` (tuple fields are designated by their index, except when
+//! they are alone).
+//!
+//! # async
+//!
+//! With the `async` feature, `#[derive(Plod)]` on a type with `#[plod(async)]` also implements
+//! `AsyncPlod`, which reads from tokio's `AsyncRead` and writes to tokio's `AsyncWrite` with the
+//! same representation. Other types are unchanged, so enabling the feature doesn't affect them.
+//!
+//! # no_std
+//!
+//! The `std` feature is enabled by default, without it plod only depends on `core` and `alloc`.
+//! Readers and writers implement the plod-owned [`PlodRead`] and [`PlodWrite`] traits, which have
+//! the same methods with or without `std`, and are implemented for `&[u8]`, `&mut [u8]` and `Vec<u8>`.
+//! With `std`, those traits are also implemented for any `std::io::Read` and `std::io::Write`,
+//! so the same `#[derive(Plod)]` types and custom readers can be shared between both environments.
 //!
 //! Document endianness and it inheritance
 //!
@@ -74,22 +137,62 @@
 //! Example, tutorial, first use
 //!
 #![deny(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
-use std::io::{Read, Write};
+extern crate alloc;
 
-/// plod results Result uses io errors
-pub type Result<T> = std::result::Result<T, std::io::Error>;
+mod io;
+pub use io::{PlodRead, PlodWrite};
+
+mod error;
+pub use error::{Error, ErrorKind, PathItem};
+
+mod impls;
+pub use impls::{BigEndian, ByteOrder, LittleEndian};
+
+mod borrow;
+pub use borrow::PlodBorrow;
+
+mod tagged;
+pub use tagged::PlodTagged;
+
+mod bits;
+mod checksum;
+mod size;
+mod string;
+
+#[cfg(feature = "async")]
+mod asynchronous;
+#[cfg(feature = "async")]
+pub use asynchronous::{AsyncPlod, AsyncPlodTagged};
+
+/// plod results use plod errors
+pub type Result<T> = core::result::Result<T, Error>;
 
 /// The main thing
 pub use plod_derive::Plod;
 
+/// Used by generated code, not part of the public API
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::bits::{BitUnit, Bitfield};
+    pub use crate::borrow::{read_exact, read_to_end, read_up_to, take, take_str, write_all};
+    pub use crate::checksum::{Checksum, ChecksumField, Recorder};
+    pub use crate::size::SizeField;
+    pub use crate::string::{check_cstr, check_fixed, decode, decode_fixed, encode, encoded_len, Encoding};
+    pub use alloc::string::ToString;
+    pub use alloc::vec::Vec;
+    #[cfg(feature = "async")]
+    pub use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+}
+
 /// The main plain old data trait.
 ///
 /// It is usually implemented using `#[derive(Plod)]`, but it can also be implemented manually to
 /// handle specific cases.
 /// The endianness is implied by implementation and not a generic type because it makes calling and writing
-/// the trait much easier and because I never found a data format that is both big and little
-/// endian (counter example anyone ?).
+/// the trait much easier. Formats that choose their byte order at runtime, like pcap, can
+/// use `#[plod(endian = dynamic)]` and a [`ByteOrder`] context instead.
 pub trait Plod: Sized {
     /// Context passed to read and write methods, if you don't need one, just use `()`
     /// The context will be passed down to any sub method called by `read_from` and `write_to`
@@ -98,32 +201,42 @@ pub trait Plod: Sized {
     /// must `impl  From<&Context> for ()` since all primitive types use `()` as a context.
     type Context;
 
+    /// Alignment in bytes of this type in C, it is used by `#[plod(repr_c)]` to insert the same
+    /// padding as a C compiler would. Primitive types use their natural alignment, derived types
+    /// compute it only with `#[plod(repr_c)]` or `#[plod(align=N)]`, they are packed otherwise.
+    const ALIGNMENT: usize = 1;
+
     /// Size once serialized (including tag if any)
-    // also used internally by byte sized Vec
     fn size_at_rest(&self) -> usize;
 
+    /// Size once serialized at position `pos`, it is different from `size_at_rest` when the data
+    /// contains alignment padding. You only need to implement it in this case.
+    fn size_at_pos(&self, _pos: usize) -> usize {
+        self.size_at_rest()
+    }
+
     /// Read this structure from a reader
-    /// Returns `std::io::Error` in case or error
-    /// Returns an error of kind `std::io::ErrorKind::Other` if an unknown enum tag was found
-    fn read_from<R: Read>(from: &mut R) -> Result<Self>
+    /// Returns a `plod::Error` in case or error, with kind `ErrorKind::UnknownTag` if an unknown
+    /// enum tag was found
+    fn read_from<R: PlodRead>(from: &mut R) -> Result<Self>
         where Self::Context : Default
     { Self::impl_read_from(from, &Self::Context::default(), 0) }
 
     /// Same as `read_from` with all parameters, you must implement this one.
     /// You should call this one if you are reading from a Plod implementation.
     /// `pos` is the position in bytes in the reader, it is used to handle padding and alignment.
-    fn impl_read_from<R: Read>(from: &mut R, ctx: &Self::Context, pos: usize) -> Result<Self>;
+    fn impl_read_from<R: PlodRead>(from: &mut R, ctx: &Self::Context, pos: usize) -> Result<Self>;
 
     /// Write this structure to a writer
-    /// Returns `std::io::Error` in case or error
-    fn write_to<W: Write>(&self, to: &mut W) -> Result<()>
+    /// Returns a `plod::Error` in case or error
+    fn write_to<W: PlodWrite>(&self, to: &mut W) -> Result<()>
         where Self::Context : Default
-    { self.impl_write_to(to, &mut Self::Context::default(), 0) }
+    { self.impl_write_to(to, &Self::Context::default(), 0) }
 
     /// Same as `write_to` with all parameters, you must implement this one.
     /// You should call this one if you are writing from a Plod implementation.
     /// `pos` is the position in bytes in the writer, it is used to handle padding and alignment.
-    fn impl_write_to<W: Write>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()>;
+    fn impl_write_to<W: PlodWrite>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()>;
 }
 
 // everything in this library is public and is tested via integration tests
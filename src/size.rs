@@ -0,0 +1,41 @@
+//! Conversions used by generated code for `#[plod(count_from=..)]`, `#[plod(len_of=..)]` and similar size fields
+
+use crate::{Error, ErrorKind, Result};
+use alloc::string::ToString;
+
+/// Integer field that contains the size of another field
+pub trait SizeField: Copy + ToString {
+    /// Convert the field value to a size, fails if it is negative or too large
+    fn to_size(self) -> Result<usize>;
+
+    /// Convert a size to a field value, fails if it doesn't fit
+    fn from_size(size: usize) -> Result<Self>;
+}
+
+macro_rules! impl_size_field {
+    ($($ty:ident),*) => {
+        $(
+        impl SizeField for $ty {
+            fn to_size(self) -> Result<usize> {
+                usize::try_from(self).map_err(|_| {
+                    Error::new(ErrorKind::InvalidValue {
+                        type_name: "usize",
+                        value: self.to_string(),
+                    })
+                })
+            }
+
+            fn from_size(size: usize) -> Result<Self> {
+                $ty::try_from(size).map_err(|_| {
+                    Error::new(ErrorKind::InvalidValue {
+                        type_name: stringify!($ty),
+                        value: size.to_string(),
+                    })
+                })
+            }
+        }
+        )*
+    };
+}
+
+impl_size_field!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
@@ -0,0 +1,177 @@
+//! Conversions used by generated code for `String` fields
+
+use crate::{Error, ErrorKind, Result};
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Text encoding of a string, chosen with `#[plod(encoding=..)]`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    /// UTF-8, the default
+    Utf8,
+    /// 7 bits ASCII, bytes above 127 are invalid
+    Ascii,
+    /// ISO-8859-1, each byte is the unicode code point of a character
+    Latin1,
+    /// UTF-16 big endian
+    Utf16Be,
+    /// UTF-16 little endian
+    Utf16Le,
+    /// UTF-16 native endian
+    Utf16Ne,
+}
+
+impl Encoding {
+    /// Size in bytes of a code unit, sizes and terminators are counted in code units
+    const fn unit(self) -> usize {
+        match self {
+            Encoding::Utf16Be | Encoding::Utf16Le | Encoding::Utf16Ne => 2,
+            _ => 1,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf8",
+            Encoding::Ascii => "ascii",
+            Encoding::Latin1 => "latin1",
+            Encoding::Utf16Be | Encoding::Utf16Le | Encoding::Utf16Ne => "utf16",
+        }
+    }
+
+    fn big_endian(self) -> bool {
+        match self {
+            Encoding::Utf16Be => true,
+            Encoding::Utf16Ne => cfg!(target_endian = "big"),
+            _ => false,
+        }
+    }
+
+    fn invalid(self, position: usize) -> Error {
+        Error::new(ErrorKind::InvalidEncoding {
+            encoding: self.name(),
+            position,
+        })
+    }
+}
+
+/// Error for bytes that are not valid UTF-8
+pub(crate) fn utf8_error(e: core::str::Utf8Error) -> Error {
+    Error::new(ErrorKind::InvalidUtf8 {
+        valid_up_to: e.valid_up_to(),
+    })
+}
+
+/// Size in bytes of a string once encoded
+pub fn encoded_len(value: &str, encoding: Encoding) -> usize {
+    match encoding {
+        Encoding::Utf8 => value.len(),
+        Encoding::Ascii | Encoding::Latin1 => value.chars().count(),
+        _ => value.encode_utf16().count() * 2,
+    }
+}
+
+/// Encode a string, characters that cannot be encoded are an error, or replaced with `?` if `lossy`
+pub fn encode(value: &str, encoding: Encoding, lossy: bool) -> Result<Cow<'_, [u8]>> {
+    let max = match encoding {
+        Encoding::Utf8 => return Ok(Cow::Borrowed(value.as_bytes())),
+        Encoding::Ascii => 0x7f,
+        Encoding::Latin1 => 0xff,
+        _ => {
+            let mut result = Vec::with_capacity(value.len() * 2);
+            for unit in value.encode_utf16() {
+                match encoding.big_endian() {
+                    true => result.extend_from_slice(&unit.to_be_bytes()),
+                    false => result.extend_from_slice(&unit.to_le_bytes()),
+                }
+            }
+            return Ok(Cow::Owned(result));
+        }
+    };
+    let mut result = Vec::with_capacity(value.len());
+    for (position, c) in value.chars().enumerate() {
+        match u8::try_from(c) {
+            Ok(byte) if byte <= max => result.push(byte),
+            _ if lossy => result.push(b'?'),
+            _ => return Err(encoding.invalid(position)),
+        }
+    }
+    Ok(Cow::Owned(result))
+}
+
+/// Decode a string, invalid data is an error, or replaced with U+FFFD if `lossy`
+pub fn decode(bytes: Vec<u8>, encoding: Encoding, lossy: bool) -> Result<String> {
+    match encoding {
+        Encoding::Utf8 if lossy => Ok(String::from_utf8_lossy(&bytes).into_owned()),
+        Encoding::Utf8 => String::from_utf8(bytes).map_err(|e| utf8_error(e.utf8_error())),
+        Encoding::Ascii => {
+            let mut result = String::with_capacity(bytes.len());
+            for (position, byte) in bytes.iter().enumerate() {
+                match byte.is_ascii() {
+                    true => result.push(*byte as char),
+                    false if lossy => result.push(char::REPLACEMENT_CHARACTER),
+                    false => return Err(encoding.invalid(position)),
+                }
+            }
+            Ok(result)
+        }
+        Encoding::Latin1 => Ok(bytes.iter().map(|b| *b as char).collect()),
+        _ => {
+            let units = bytes.chunks_exact(2).map(|unit| match encoding.big_endian() {
+                true => u16::from_be_bytes([unit[0], unit[1]]),
+                false => u16::from_le_bytes([unit[0], unit[1]]),
+            });
+            let mut result = String::with_capacity(bytes.len() / 2);
+            let mut position = 0;
+            for c in char::decode_utf16(units) {
+                match c {
+                    Ok(c) => result.push(c),
+                    Err(_) if lossy => result.push(char::REPLACEMENT_CHARACTER),
+                    Err(_) => return Err(encoding.invalid(position)),
+                }
+                position += c.map_or(2, |c| c.len_utf16() * 2);
+            }
+            // an odd number of bytes cannot be a complete code unit
+            if bytes.len() % 2 == 1 {
+                match lossy {
+                    true => result.push(char::REPLACEMENT_CHARACTER),
+                    false => return Err(encoding.invalid(bytes.len() - 1)),
+                }
+            }
+            Ok(result)
+        }
+    }
+}
+
+/// Decode a fixed width string, trailing code units made of `fill` bytes are removed
+pub fn decode_fixed(bytes: &[u8], fill: u8, encoding: Encoding, lossy: bool) -> Result<String> {
+    let unit = encoding.unit();
+    let mut end = bytes.len();
+    while end >= unit && bytes[end - unit..end].iter().all(|b| *b == fill) {
+        end -= unit;
+    }
+    decode(bytes[..end].to_vec(), encoding, lossy)
+}
+
+/// Check that an encoded string fits in `size` bytes
+pub fn check_fixed(encoded: &[u8], size: usize) -> Result<()> {
+    if encoded.len() > size {
+        return Err(Error::new(ErrorKind::TooLong {
+            size,
+            found: encoded.len(),
+        }));
+    }
+    Ok(())
+}
+
+/// Check that a string can be written as a C string, ie: it doesn't contain a null character
+pub fn check_cstr(value: &str) -> Result<()> {
+    if value.contains('\0') {
+        return Err(Error::new(ErrorKind::InvalidValue {
+            type_name: "cstr",
+            value: value.to_string(),
+        }));
+    }
+    Ok(())
+}
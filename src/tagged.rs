@@ -0,0 +1,48 @@
+//! Enums whose tag is stored apart from their variants
+
+use crate::{Plod, PlodRead, PlodWrite, Result};
+
+/// Enum whose tag can be stored in an earlier field of the enclosing struct, implemented by
+/// `#[derive(Plod)]` for enums and used by `#[plod(tag_from = <field>)]`.
+///
+/// ```
+/// use plod::Plod;
+///
+/// #[derive(Plod, PartialEq, Debug)]
+/// #[plod(tag_type(u8))]
+/// enum Body {
+///     #[plod(tag = 1)]
+///     Ping(u16),
+///     #[plod(tag = 2)]
+///     Pong,
+/// }
+///
+/// #[derive(Plod, PartialEq, Debug)]
+/// struct Message {
+///     msg_type: u8,
+///     len: u16,
+///     #[plod(tag_from = msg_type)]
+///     body: Body,
+/// }
+///
+/// let message = Message { msg_type: 1, len: 2, body: Body::Ping(3) };
+/// let mut memory: Vec<u8> = Vec::new();
+/// message.write_to(&mut memory).unwrap();
+/// assert_eq!(memory.len(), 5);
+/// assert_eq!(Message::read_from(&mut memory.as_slice()).unwrap(), message);
+/// ```
+pub trait PlodTagged: Plod {
+    /// Type of the tag, the enum `tag_type`
+    type Tag;
+
+    /// Size once serialized at position `pos`, without the tag
+    fn untagged_size_at_pos(&self, pos: usize) -> usize;
+
+    /// Read the variant selected by `tag`, the tag itself has already been read.
+    /// Returns an error with kind `ErrorKind::UnknownTag` if no variant matches it.
+    fn impl_read_untagged<R: PlodRead>(tag: Self::Tag, from: &mut R, ctx: &Self::Context, pos: usize) -> Result<Self>;
+
+    /// Write this variant without its tag.
+    /// Returns an error with kind `ErrorKind::TagMismatch` if `tag` would not select this variant.
+    fn impl_write_untagged<W: PlodWrite>(&self, tag: Self::Tag, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()>;
+}
@@ -0,0 +1,192 @@
+use plod::{AsyncPlod, ErrorKind, Plod};
+use tokio::io::AsyncWriteExt;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(async, big_endian, tag_type(u8))]
+enum Body {
+    #[plod(tag = 1)]
+    Ping(u32),
+    #[plod(tag = 2, size_type(u16))]
+    Data(Vec<u8>),
+    #[plod(tag = 3, size_type(u8), byte_sized)]
+    Values(Vec<(u16, bool)>),
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(async, big_endian, magic(u16 = 0xf00d))]
+struct Frame {
+    id: u32,
+    #[plod(size_type(u16))]
+    bodies: Vec<Body>,
+    trailer: [u8; 2],
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(async, big_endian)]
+struct Trailing {
+    kind: u8,
+    #[plod(cstr)]
+    name: String,
+    #[plod(rest)]
+    values: Vec<u16>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(async, big_endian)]
+struct Checked {
+    len: u8,
+    #[plod(count_from = len)]
+    data: Vec<u8>,
+    #[plod(checksum = adler32, from = data)]
+    sum: u32,
+}
+
+#[derive(PartialEq, Debug, Default)]
+struct Context {
+    shift: u8,
+}
+
+impl From<&Context> for &() {
+    fn from(_: &Context) -> Self {
+        &()
+    }
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(async, context = Context)]
+struct WithContext {
+    a: u16,
+    b: Frame,
+}
+
+/// Bytes stored inverted
+mod inverted {
+    use plod::{PlodRead, PlodWrite, Result};
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+    pub fn read<R: PlodRead>(from: &mut R, _ctx: &(), _pos: usize) -> Result<u8> {
+        let mut byte = [0];
+        from.read_exact(&mut byte)?;
+        Ok(!byte[0])
+    }
+
+    pub fn write<W: PlodWrite>(value: &u8, to: &mut W, _ctx: &(), _pos: usize) -> Result<()> {
+        to.write_all(&[!value])
+    }
+
+    pub fn size(_value: &u8, _pos: usize) -> usize {
+        1
+    }
+
+    pub async fn read_async<R: AsyncRead + Unpin>(from: &mut R, _ctx: &(), _pos: usize) -> Result<u8> {
+        Ok(!from.read_u8().await?)
+    }
+
+    pub async fn write_async<W: AsyncWrite + Unpin>(value: &u8, to: &mut W, _ctx: &(), _pos: usize) -> Result<()> {
+        to.write_u8(!value).await?;
+        Ok(())
+    }
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(async)]
+struct Inverted {
+    #[plod(with = inverted)]
+    a: u8,
+    #[plod(read_with = inverted::read, write_with = inverted::write)]
+    b: u8,
+}
+
+fn frame() -> Frame {
+    Frame {
+        id: 7,
+        bodies: vec![
+            Body::Ping(1),
+            Body::Data(vec![1, 2, 3]),
+            Body::Values(vec![(4, true), (5, false)]),
+        ],
+        trailer: [8, 9],
+    }
+}
+
+#[tokio::test]
+async fn test_async() {
+    let frame = frame();
+    let mut sync_memory: Vec<u8> = Vec::new();
+    frame.write_to(&mut sync_memory).unwrap();
+
+    let mut memory: Vec<u8> = Vec::new();
+    frame.write_to_async(&mut memory).await.unwrap();
+    assert_eq!(memory, sync_memory);
+
+    let result = Frame::read_from_async(&mut memory.as_slice()).await.unwrap();
+    assert_eq!(result, frame);
+
+    // a stream that doesn't provide the whole frame at once
+    let (mut client, mut server) = tokio::io::duplex(4);
+    let write = async move {
+        for chunk in memory.chunks(3) {
+            client.write_all(chunk).await.unwrap();
+        }
+    };
+    let (_, result) = tokio::join!(write, Frame::read_from_async(&mut server));
+    assert_eq!(result.unwrap(), frame);
+
+    let err = Frame::read_from_async(&mut &sync_memory[..14]).await.unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+    assert_eq!(err.offset(), Some(14));
+    assert_eq!(err.path(), "Frame.bodies[1].Data");
+
+    let trailing = Trailing {
+        kind: 1,
+        name: "a".into(),
+        values: vec![2, 3],
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    trailing.write_to_async(&mut memory).await.unwrap();
+    assert_eq!(memory, [1, b'a', 0, 0, 2, 0, 3]);
+    let result = Trailing::read_from_async(&mut memory.as_slice()).await.unwrap();
+    assert_eq!(result, trailing);
+
+    let checked = Checked {
+        len: 9,
+        data: b"Wikipedia".to_vec(),
+        sum: 0,
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    checked.write_to_async(&mut memory).await.unwrap();
+    assert_eq!(memory[10..], [0x11, 0xe6, 0x03, 0x98]);
+    let result = Checked::read_from_async(&mut memory.as_slice()).await.unwrap();
+    assert_eq!(result.sum, 0x11e6_0398);
+    memory[1] = b'w';
+    let err = Checked::read_from_async(&mut memory.as_slice()).await.unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::BadChecksum { .. }));
+}
+
+#[tokio::test]
+async fn test_async_context() {
+    let value = WithContext { a: 1, b: frame() };
+    let ctx = Context { shift: 1 };
+    let mut memory: Vec<u8> = Vec::new();
+    value.impl_write_to_async(&mut memory, &ctx, 0).await.unwrap();
+    let result = WithContext::impl_read_from_async(&mut memory.as_slice(), &ctx, 0).await.unwrap();
+    assert_eq!(result, value);
+
+    let memory = [0xf0, 0x0e, 0, 0, 0, 0, 0, 0];
+    let err = Frame::read_from_async(&mut memory.as_slice()).await.unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::BadMagic { .. }));
+}
+
+#[tokio::test]
+async fn test_async_with() {
+    // async codecs are the sync ones with an _async suffix
+    let value = Inverted { a: 1, b: 2 };
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to_async(&mut memory).await.unwrap();
+    assert_eq!(memory, [0xfe, 0xfd]);
+    let mut sync_memory: Vec<u8> = Vec::new();
+    value.write_to(&mut sync_memory).unwrap();
+    assert_eq!(memory, sync_memory);
+    let result = Inverted::read_from_async(&mut memory.as_slice()).await.unwrap();
+    assert_eq!(result, value);
+}
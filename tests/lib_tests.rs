@@ -1,7 +1,7 @@
 use std::cell::RefCell;
 use plod::*;
 use std::fmt::Debug;
-use std::io::{Read, Write};
+use std::io::Read;
 
 #[derive(Plod, PartialEq, Debug)]
 #[plod(tag_type(u8))]
@@ -43,13 +43,13 @@ struct PosMarker {
 impl Plod for PosMarker {
     type Context = ();
     fn size_at_rest(&self) -> usize { 1 }
-    fn impl_read_from<R: Read>(from: &mut R, _ctx: &Self::Context, pos: usize) -> Result<Self> {
+    fn impl_read_from<R: PlodRead>(from: &mut R, _ctx: &Self::Context, pos: usize) -> Result<Self> {
         let mut data = [0_u8; 1];
         from.read_exact(&mut data)?;
         Ok(PosMarker{pos: RefCell::new(pos)})
     }
 
-    fn impl_write_to<W: Write>(&self, to: &mut W, _ctx: &Self::Context, pos: usize) -> Result<()> {
+    fn impl_write_to<W: PlodWrite>(&self, to: &mut W, _ctx: &Self::Context, pos: usize) -> Result<()> {
         self.pos.replace(pos);
         let data = [0_u8; 1];
         to.write_all(&data)?;
@@ -334,14 +334,15 @@ impl Plod for TestWithContext2 {
         2
     }
 
-    fn impl_read_from<R: Read>(_form: &mut R, ctx: &Self::Context, _pos: usize) -> Result<Self> {
+    fn impl_read_from<R: PlodRead>(_form: &mut R, ctx: &Self::Context, _pos: usize) -> Result<Self> {
         let a = ctx.get() as u16;
         Ok(TestWithContext2 { a })
     }
 
-    fn impl_write_to<W: Write>(&self, to: &mut W, _ctx: &Self::Context, _pos: usize) -> Result<()> {
+    fn impl_write_to<W: PlodWrite>(&self, to: &mut W, _ctx: &Self::Context, _pos: usize) -> Result<()> {
         let buffer: [u8; 2] = self.a.to_ne_bytes();
-        to.write_all(&buffer)
+        to.write_all(&buffer)?;
+        Ok(())
     }
 }
 
@@ -369,5 +370,1421 @@ struct TestPartialContext {
     b: TestWithContext,
 }
 
-// TODO test with generic in struct
+#[test]
+fn test_partial_context() {
+    let val = TestPartialContext {
+        a: 1,
+        c: Context { count: 2 },
+        b: TestWithContext {
+            a: 3,
+            b: TestWithContext2 { a: 2 },
+        },
+    };
+    it_reads_what_it_writes(&val);
+}
+
+#[test]
+fn test_error() {
+    let s3 = TestStruct3 {
+        a: vec![],
+        b: TestStruct2(1, TestEnum1::A { x: 1, y: 2, z: 3 }),
+        c: TestEnum2::D(vec![
+            TestEnum1::A { x: 1, y: 2, z: 3 },
+            TestEnum1::A { x: 4, y: 5, z: 6 },
+            TestEnum1::B { x: 7, val: vec![8, 9] },
+        ]),
+        d: TestEnum2::B(),
+        e: TestEnum2::C,
+        f: TestEnum2::C,
+        g: TestEnum2::C,
+        h: TestEnum2::C,
+        i: TestEnum2::C,
+        j: [1, 2, 3],
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(s3.write_to(&mut memory).is_ok());
+
+    // cut in the middle of the last i16 of c
+    let offset = 2 + (2 + 20) + 1 + 2 + 20 + 20 + (1 + 1 + 4 + 2);
+    let mut mem = std::io::Cursor::new(&memory[..offset + 1]);
+    let err = TestStruct3::read_from(&mut mem).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+    assert_eq!(err.offset(), Some(offset));
+    assert_eq!(err.path(), "TestStruct3.c.D[2].B.val[1]");
+
+    // corrupt the tag of b.1
+    let offset = 2 + 2;
+    memory[offset] = 9;
+    let mut mem = std::io::Cursor::new(memory);
+    let err = TestStruct3::read_from(&mut mem).unwrap_err();
+    match err.kind() {
+        ErrorKind::UnknownTag { type_name, value } => {
+            assert_eq!(*type_name, "TestEnum1");
+            assert_eq!(value, "9");
+        }
+        _ => panic!("unexpected error {}", err),
+    }
+    assert_eq!(err.offset(), Some(offset));
+    assert_eq!(err.path(), "TestStruct3.b.1");
+
+    let mut memory: Vec<u8> = Vec::new();
+    let err = TestEnum1::D(0).write_to(&mut memory).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::SkippedVariant { variant: "D" }));
+
+    let memory = vec![0xab, 0xce, 0x12, 0x34];
+    let err = TestMagic::read_from(&mut memory.as_slice()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::BadMagic { .. }));
+    assert_eq!(err.offset(), Some(0));
+    assert_eq!(err.to_string(), "magic value 43981 expected, found 43982 at offset 0 in TestMagic");
+}
+
+#[derive(PartialEq, Debug)]
+struct TestManual {
+    a: u32,
+    b: (bool, BigEndian<u16>),
+}
+
+impl Plod for TestManual {
+    type Context = ();
+
+    fn size_at_rest(&self) -> usize {
+        self.a.size_at_rest() + self.b.size_at_rest()
+    }
+
+    fn impl_read_from<R: PlodRead>(from: &mut R, ctx: &Self::Context, pos: usize) -> Result<Self> {
+        let a = u32::impl_read_from(from, ctx, pos)?;
+        let b = <(bool, BigEndian<u16>)>::impl_read_from(from, ctx, pos + 4)?;
+        Ok(TestManual { a, b })
+    }
+
+    fn impl_write_to<W: PlodWrite>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()> {
+        self.a.impl_write_to(to, ctx, pos)?;
+        self.b.impl_write_to(to, ctx, pos + 4)
+    }
+}
+
+#[test]
+fn test_runtime_impls() {
+    let val = TestManual {
+        a: 0x01020304,
+        b: (true, BigEndian(0x0506)),
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, [0x01020304_u32.to_ne_bytes().as_slice(), &[1, 5, 6]].concat());
+    it_reads_what_it_writes(&val);
+
+    it_reads_what_it_writes(&TestGeneric { a: 12_u32 });
+    it_reads_what_it_writes(&TestGeneric { a: LittleEndian(-3_i64) });
+    it_reads_what_it_writes(&TestGeneric { a: [TestEnum2::C, TestEnum2::E(5, 1)] });
+    it_reads_what_it_writes(&TestGeneric { a: (1.5_f32, (), [false; 2]) });
+
+    let err = bool::read_from(&mut [2_u8].as_slice()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidValue { type_name: "bool", .. }));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(borrow, big_endian)]
+struct TestBorrowed<'a> {
+    a: u16,
+    #[plod(size_type(u8))]
+    name: &'a str,
+    id: &'a [u8; 2],
+    inner: TestBorrowedInner<'a>,
+    b: BigEndian<u16>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(borrow, tag_type(u8), little_endian)]
+enum TestBorrowedInner<'a> {
+    #[plod(tag = 1)]
+    Data(
+        #[plod(size_type(u16), byte_sized)]
+        &'a [u8],
+    ),
+    #[plod(tag = 2)]
+    List(#[plod(size_type(u8), byte_sized)] Vec<TestBorrowedInner<'a>>),
+}
+
+#[test]
+fn test_borrow() {
+    let data = [
+        0, 7, 3, b'a', b'b', b'c', 9, 8, 2, 7, 1, 1, 0, 4, 1, 0, 0, 0, 6, 0xff,
+    ];
+    let (val, rest) = TestBorrowed::parse(&data).unwrap();
+    assert_eq!(val.a, 7);
+    assert_eq!(val.name, "abc");
+    assert_eq!(val.name.as_ptr(), data[3..].as_ptr());
+    assert_eq!(val.id, &[9, 8]);
+    assert_eq!(
+        val.inner,
+        TestBorrowedInner::List(vec![
+            TestBorrowedInner::Data(&[4]),
+            TestBorrowedInner::Data(&[]),
+        ])
+    );
+    assert_eq!(val.b, BigEndian(6));
+    assert_eq!(rest, &[0xff]);
+
+    let err = TestBorrowed::parse(&data[..5]).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+    assert_eq!(err.offset(), Some(3));
+    assert_eq!(err.path(), "TestBorrowed.name");
+
+    let mut bad = data;
+    bad[4] = 0xff;
+    let err = TestBorrowed::parse(&bad).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidUtf8 { valid_up_to: 1 }));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, align = 4, fill = 0xff)]
+struct TestAlign {
+    a: u8,
+    #[plod(align = 4)]
+    b: u32,
+    #[plod(pad = 2)]
+    c: u8,
+    d: TestAlignInner,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(align = 8, check_padding)]
+struct TestAlignInner {
+    a: u8,
+}
+
+#[test]
+fn test_padding() {
+    let val = TestAlign {
+        a: 1,
+        b: 2,
+        c: 3,
+        d: TestAlignInner { a: 4 },
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(
+        memory,
+        [1, 0xff, 0xff, 0xff, 0, 0, 0, 2, 0xff, 0xff, 3, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0]
+    );
+    assert_eq!(val.size_at_rest(), 24);
+    assert_eq!(val.d.size_at_rest(), 8);
+    assert_eq!(val.d.size_at_pos(3), 13);
+    it_reads_what_it_writes(&val);
+
+    // padding is only checked with check_padding
+    memory[1] = 0;
+    assert_eq!(TestAlign::read_from(&mut memory.as_slice()).unwrap(), val);
+    memory[12] = 1;
+    let err = TestAlign::read_from(&mut memory.as_slice()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::BadPadding { expected: 0, found: 1 }));
+    assert_eq!(err.offset(), Some(12));
+    assert_eq!(err.path(), "TestAlign.d");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(repr_c)]
+struct TestReprC {
+    a: u8,
+    b: u32,
+    c: u16,
+    d: TestReprCInner,
+    e: [u16; 3],
+    f: u8,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(repr_c)]
+struct TestReprCInner {
+    a: u64,
+    b: u8,
+    c: i16,
+}
+
+#[repr(C)]
+struct ReprC {
+    a: u8,
+    b: u32,
+    c: u16,
+    d: ReprCInner,
+    e: [u16; 3],
+    f: u8,
+}
+
+#[repr(C)]
+struct ReprCInner {
+    a: u64,
+    b: u8,
+    c: i16,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, repr_c)]
+struct TestReprCNested {
+    a: u8,
+    b: TestReprCPacked,
+    c: u16,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestReprCPacked {
+    a: u64,
+    b: u8,
+    c: i16,
+}
+
+#[repr(C)]
+struct ReprCNested {
+    a: u8,
+    b: ReprCPacked,
+    c: u16,
+}
+
+#[repr(C, packed)]
+struct ReprCPacked {
+    a: u64,
+    b: u8,
+    c: i16,
+}
+
+#[test]
+fn test_repr_c() {
+    let val = TestReprC {
+        a: 1,
+        b: 2,
+        c: 3,
+        d: TestReprCInner { a: 4, b: 5, c: 6 },
+        e: [7, 8, 9],
+        f: 10,
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory.len(), std::mem::size_of::<ReprC>());
+    assert_eq!(val.size_at_rest(), std::mem::size_of::<ReprC>());
+    assert_eq!(TestReprC::ALIGNMENT, std::mem::align_of::<ReprC>());
+    let offsets = [
+        (std::mem::offset_of!(ReprC, b), 2_u8),
+        (std::mem::offset_of!(ReprC, c), 3),
+        (std::mem::offset_of!(ReprC, d) + std::mem::offset_of!(ReprCInner, a), 4),
+        (std::mem::offset_of!(ReprC, d) + std::mem::offset_of!(ReprCInner, b), 5),
+        (std::mem::offset_of!(ReprC, d) + std::mem::offset_of!(ReprCInner, c), 6),
+        (std::mem::offset_of!(ReprC, e), 7),
+        (std::mem::offset_of!(ReprC, f), 10),
+    ];
+    for (offset, value) in offsets {
+        assert_eq!(memory[offset], value, "offset {}", offset);
+    }
+    it_reads_what_it_writes(&val);
+
+    // a nested struct without repr_c is packed
+    let val = TestReprCNested {
+        a: 1,
+        b: TestReprCPacked { a: 2, b: 3, c: 4 },
+        c: 5,
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory.len(), std::mem::size_of::<ReprCNested>());
+    assert_eq!(TestReprCPacked::ALIGNMENT, std::mem::align_of::<ReprCPacked>());
+    assert_eq!(TestReprCNested::ALIGNMENT, std::mem::align_of::<ReprCNested>());
+    let offsets = [
+        (std::mem::offset_of!(ReprCNested, b) + std::mem::offset_of!(ReprCPacked, a), 2_u8),
+        (std::mem::offset_of!(ReprCNested, b) + std::mem::offset_of!(ReprCPacked, b), 3),
+        (std::mem::offset_of!(ReprCNested, b) + std::mem::offset_of!(ReprCPacked, c), 4),
+        (std::mem::offset_of!(ReprCNested, c), 5),
+    ];
+    for (offset, value) in offsets {
+        assert_eq!(memory[offset], value, "offset {}", offset);
+    }
+    it_reads_what_it_writes(&val);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestBitfields {
+    #[plod(bits = 4)]
+    version: u8,
+    #[plod(bits = 4)]
+    ihl: u8,
+    #[plod(bits = 4)]
+    data_offset: u16,
+    #[plod(bits = 3)]
+    reserved: u8,
+    #[plod(bits = 1)]
+    ns: bool,
+    #[plod(bits = 8)]
+    flags: u8,
+    #[plod(little_endian, lsb_first, bits = 3)]
+    a: i32,
+    #[plod(bits = 29)]
+    b: u32,
+    c: u8,
+}
+
+#[test]
+fn test_bitfields() {
+    let mut val = TestBitfields {
+        version: 4,
+        ihl: 5,
+        data_offset: 5,
+        reserved: 0,
+        ns: true,
+        flags: 0x12,
+        a: -3,
+        b: 7,
+        c: 9,
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, [0x45, 0x51, 0x12, 0x3d, 0, 0, 0, 9]);
+    assert_eq!(val.size_at_rest(), 8);
+    it_reads_what_it_writes(&val);
+    val.a = -4;
+    it_reads_what_it_writes(&val);
+
+    val.a = 4;
+    let err = val.write_to(&mut Vec::new()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::BitfieldOverflow { bits: 3, .. }));
+    assert_eq!(err.to_string(), "value 4 does not fit in 3 bits at offset 3 in TestBitfields.a");
+    val.a = 0;
+    val.version = 16;
+    let err = val.write_to(&mut Vec::new()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::BitfieldOverflow { bits: 4, .. }));
+    assert_eq!(err.path(), "TestBitfields.version");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestSizeFrom {
+    n_entries: u16,
+    len: u8,
+    #[plod(count_from = n_entries)]
+    entries: Vec<u32>,
+    #[plod(bytes_from = len)]
+    data: Vec<i16>,
+    other: TestSizeFromEnum,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum TestSizeFromEnum {
+    #[plod(tag = 1)]
+    A {
+        n: u8,
+        #[plod(count_from = n)]
+        v: Vec<u8>,
+    },
+}
+
+#[test]
+fn test_size_from() {
+    let mut val = TestSizeFrom {
+        n_entries: 2,
+        len: 6,
+        entries: vec![1, 2],
+        data: vec![3, 4, 5],
+        other: TestSizeFromEnum::A { n: 1, v: vec![6] },
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, [2, 0, 6, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 4, 0, 5, 0, 1, 1, 6]);
+    assert_eq!(val.size_at_rest(), memory.len());
+    it_reads_what_it_writes(&val);
+
+    val.data.push(6);
+    let err = val.write_to(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.to_string(), "size 8 does not match len = 6 at offset 11 in TestSizeFrom.data");
+    val.data.pop();
+    val.other = TestSizeFromEnum::A { n: 1, v: vec![] };
+    let err = val.write_to(&mut Vec::new()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::SizeMismatch { size_field: "n", expected: 1, found: 0 }));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestRest {
+    #[plod(size_type(u8), byte_sized)]
+    header: Vec<TestRestItem>,
+    #[plod(rest)]
+    payload: Vec<u16>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct TestRestItem {
+    kind: u8,
+    #[plod(rest)]
+    data: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(borrow)]
+struct TestBorrowedRest<'a> {
+    a: u8,
+    #[plod(rest)]
+    rest: &'a [u8],
+}
+
+#[test]
+fn test_rest() {
+    let val = TestRest {
+        header: vec![TestRestItem {
+            kind: 7,
+            data: vec![1, 2],
+        }],
+        payload: vec![5, 6],
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, [3, 7, 1, 2, 5, 0, 6, 0]);
+    it_reads_what_it_writes(&val);
+
+    // the last item must be complete
+    memory.pop();
+    let err = TestRest::read_from(&mut memory.as_slice()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+    assert_eq!(err.path(), "TestRest.payload[1]");
+
+    let (val, rest) = TestBorrowedRest::parse(&memory).unwrap();
+    assert_eq!(val.a, 3);
+    assert_eq!(val.rest, &memory[1..]);
+    assert!(rest.is_empty());
+
+    // interrupted reads are retried
+    let memory = [3, 7, 1, 2, 5, 0, 6, 0];
+    let mut reader = InterruptedReader { data: &memory, interrupted: false };
+    let val = TestRest::read_from(&mut reader).unwrap();
+    assert_eq!(val.header[0].data, vec![1, 2]);
+    assert_eq!(val.payload, vec![5, 6]);
+}
+
+/// Reader that fails with `Interrupted` before each successful read
+struct InterruptedReader<'a> {
+    data: &'a [u8],
+    interrupted: bool,
+}
+
+impl Read for InterruptedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.interrupted = !self.interrupted;
+        if self.interrupted {
+            return Err(std::io::ErrorKind::Interrupted.into());
+        }
+        Read::read(&mut self.data, buf)
+    }
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestTerminator {
+    #[plod(terminator = 0xffff)]
+    values: Vec<u16>,
+    #[plod(terminator = 0)]
+    name: Vec<u8>,
+    #[plod(terminator = TestTerminatorEntry { kind: 0, value: 0 })]
+    entries: Vec<TestTerminatorEntry>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct TestTerminatorEntry {
+    kind: u8,
+    value: u8,
+}
+
+#[test]
+fn test_terminator() {
+    let val = TestTerminator {
+        values: vec![1, 2],
+        name: vec![b'a', b'b'],
+        entries: vec![TestTerminatorEntry { kind: 1, value: 0 }],
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, [0, 1, 0, 2, 0xff, 0xff, b'a', b'b', 0, 1, 0, 0, 0]);
+    assert_eq!(val.size_at_rest(), memory.len());
+    it_reads_what_it_writes(&val);
+
+    // a missing terminator is an early end of data
+    let err = TestTerminator::read_from(&mut &memory[..11]).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+    assert_eq!(err.path(), "TestTerminator.entries[1].kind");
+
+    // an item cannot be equal to the terminator
+    let val = TestTerminator {
+        values: vec![1, 2],
+        name: vec![b'a', 0, b'b'],
+        entries: vec![],
+    };
+    let err = val.write_to(&mut Vec::new()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidValue { type_name: "terminated item", .. }));
+    assert_eq!(err.offset(), Some(7));
+    assert_eq!(err.path(), "TestTerminator.name[1]");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, size_type(u8))]
+struct TestString {
+    sized: String,
+    #[plod(cstr)]
+    c_string: String,
+    #[plod(fixed = 6, fill = 0x20)]
+    fixed: String,
+    len: u16,
+    #[plod(bytes_from = len)]
+    from_len: String,
+    #[plod(rest)]
+    rest: String,
+}
+
+#[test]
+fn test_string() {
+    let val = TestString {
+        sized: "ab".into(),
+        c_string: "cd".into(),
+        fixed: "é".into(),
+        len: 1,
+        from_len: "f".into(),
+        rest: "gh".into(),
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(
+        memory,
+        [2, b'a', b'b', b'c', b'd', 0, 0xc3, 0xa9, 0x20, 0x20, 0x20, 0x20, 0, 1, b'f', b'g', b'h']
+    );
+    assert_eq!(val.size_at_rest(), memory.len());
+    it_reads_what_it_writes(&val);
+
+    memory[1] = 0xff;
+    let err = TestString::read_from(&mut memory.as_slice()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidUtf8 { valid_up_to: 0 }));
+    assert_eq!(err.path(), "TestString.sized");
+
+    let val = TestString {
+        fixed: "1234567".into(),
+        ..val
+    };
+    let err = val.write_to(&mut Vec::new()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::TooLong { size: 6, found: 7 }));
+    assert_eq!(err.offset(), Some(6));
+
+    let val = TestString {
+        fixed: "".into(),
+        c_string: "a\0b".into(),
+        ..val
+    };
+    let err = val.write_to(&mut Vec::new()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidValue { .. }));
+    assert_eq!(err.path(), "TestString.c_string");
+
+    // a size bigger than the data is not allocated
+    let err = TestHugeSize::read_from(&mut &[0xff; 8][..]).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+    assert_eq!(err.path(), "TestHugeSize.name");
+    let err = TestHugeSize::read_from(&mut &[0, 0, 0, 0, 0, 0, 0, 1, b'a', 0xff, 0xff, 0xff, 0xff, 1][..]).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+    assert_eq!(err.path(), "TestHugeSize.data");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestHugeSize {
+    #[plod(size_type(u64))]
+    name: String,
+    #[plod(size_type(u32))]
+    data: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, size_type(u8), encoding = "utf16")]
+struct TestEncoding {
+    utf16: String,
+    #[plod(big_endian, cstr)]
+    utf16_be: String,
+    #[plod(encoding = "latin1", fixed = 4)]
+    latin1: String,
+    #[plod(encoding = "ascii", byte_sized)]
+    ascii: String,
+    #[plod(encoding = "ascii", lossy, rest)]
+    lossy: String,
+}
+
+#[test]
+fn test_encoding() {
+    let val = TestEncoding {
+        utf16: "a€".into(),
+        utf16_be: "b".into(),
+        latin1: "é".into(),
+        ascii: "c".into(),
+        lossy: "d".into(),
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(
+        memory,
+        [2, b'a', 0, 0xac, 0x20, 0, b'b', 0, 0, 0xe9, 0, 0, 0, 1, b'c', b'd']
+    );
+    assert_eq!(val.size_at_rest(), memory.len());
+    it_reads_what_it_writes(&val);
+
+    // a lone surrogate
+    memory[4] = 0xd8;
+    let err = TestEncoding::read_from(&mut memory.as_slice()).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::InvalidEncoding {
+            encoding: "utf16",
+            position: 2
+        }
+    ));
+    memory[4] = 0x20;
+
+    memory[15] = 0xff;
+    let val = TestEncoding::read_from(&mut memory.as_slice()).unwrap();
+    assert_eq!(val.lossy, "\u{fffd}");
+    memory[14] = 0xff;
+    let err = TestEncoding::read_from(&mut memory.as_slice()).unwrap_err();
+    assert_eq!(err.path(), "TestEncoding.ascii");
+
+    let val = TestEncoding {
+        latin1: "€".into(),
+        ..val
+    };
+    let err = val.write_to(&mut Vec::new()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidEncoding { position: 0, .. }));
+    assert_eq!(err.path(), "TestEncoding.latin1");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestCondition {
+    flags: u8,
+    #[plod(if = "flags & 0x1 != 0")]
+    extension: Option<u16>,
+    #[plod(if = "*flags & 0x2 != 0", size_type(u8))]
+    names: Option<Vec<u8>>,
+    #[plod(if = extension.is_some())]
+    more: Option<TestConditionVariant>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum TestConditionVariant {
+    #[plod(tag = 1)]
+    A {
+        kind: u8,
+        #[plod(if = "*kind == 2")]
+        value: Option<u32>,
+    },
+}
+
+#[test]
+fn test_condition() {
+    let val = TestCondition {
+        flags: 3,
+        extension: Some(4),
+        names: Some(vec![5]),
+        more: Some(TestConditionVariant::A {
+            kind: 2,
+            value: Some(6),
+        }),
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, [3, 4, 0, 1, 5, 1, 2, 6, 0, 0, 0]);
+    assert_eq!(val.size_at_rest(), memory.len());
+    it_reads_what_it_writes(&val);
+
+    let val = TestCondition {
+        flags: 0,
+        extension: None,
+        names: None,
+        more: None,
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, [0]);
+    it_reads_what_it_writes(&val);
+
+    let val = TestCondition {
+        flags: 2,
+        ..val
+    };
+    let err = val.write_to(&mut Vec::new()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::OptionMismatch { condition: true }));
+    assert_eq!(err.path(), "TestCondition.names");
+    assert_eq!(err.offset(), Some(1));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestPresence {
+    #[plod(presence(u8))]
+    a: Option<u16>,
+    #[plod(presence(u16, present = 0xffff, absent = 0x8000))]
+    b: Option<(u8, u8)>,
+}
+
+#[test]
+fn test_presence() {
+    let val = TestPresence {
+        a: Some(2),
+        b: None,
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, [1, 0, 2, 0x80, 0]);
+    assert_eq!(val.size_at_rest(), memory.len());
+    it_reads_what_it_writes(&val);
+
+    let val = TestPresence {
+        a: None,
+        b: Some((3, 4)),
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, [0, 0xff, 0xff, 3, 4]);
+    it_reads_what_it_writes(&val);
+
+    memory[0] = 2;
+    let err = TestPresence::read_from(&mut memory.as_slice()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidValue { type_name: "Option", .. }));
+    assert_eq!(err.path(), "TestPresence.a");
+}
+
+#[derive(Plod, PartialEq, Debug, Clone)]
+#[plod(tag_type(u8))]
+enum TestTree {
+    #[plod(tag = 0)]
+    Leaf(u8),
+    #[plod(tag = 1)]
+    Node(Box<TestTree>, Box<TestTree>),
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct TestPointers<'a> {
+    tree: Box<TestTree>,
+    rc: std::rc::Rc<u16>,
+    arc: std::sync::Arc<[u8; 2]>,
+    cow: std::borrow::Cow<'a, TestTree>,
+}
+
+#[test]
+fn test_pointers() {
+    let tree = TestTree::Node(
+        Box::new(TestTree::Leaf(1)),
+        Box::new(TestTree::Node(Box::new(TestTree::Leaf(2)), Box::new(TestTree::Leaf(3)))),
+    );
+    let val = TestPointers {
+        tree: Box::new(tree.clone()),
+        rc: std::rc::Rc::new(4),
+        arc: std::sync::Arc::new([5, 6]),
+        cow: std::borrow::Cow::Borrowed(&tree),
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(&memory[..9], [1, 0, 1, 1, 0, 2, 0, 3, 4]);
+    assert_eq!(val.size_at_rest(), memory.len());
+    it_reads_what_it_writes(&val);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, size_type(u8))]
+struct TestCollections {
+    map: std::collections::BTreeMap<u8, String>,
+    #[plod(byte_sized)]
+    hash_map: std::collections::HashMap<u16, u8>,
+    set: std::collections::BTreeSet<u8>,
+    #[plod(size_is_next)]
+    deque: std::collections::VecDeque<u16>,
+    boxed: Box<[u8]>,
+    #[plod(terminator = (0, 0))]
+    terminated: std::collections::BTreeMap<u8, u8>,
+}
+
+#[test]
+fn test_collections() {
+    let val = TestCollections {
+        map: [(2, "b".to_string()), (1, "a".to_string())].into(),
+        hash_map: [(3, 4)].into(),
+        set: [6, 5].into(),
+        deque: [7].into(),
+        boxed: Box::new([8, 9]),
+        terminated: [(10, 11)].into(),
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(
+        memory,
+        [
+            2, 1, 1, b'a', 2, 1, b'b', // map is sorted
+            3, 0, 3, 4, // hash_map
+            2, 5, 6, // set is sorted
+            2, 0, 7, // deque
+            2, 8, 9, // boxed
+            10, 11, 0, 0, // terminated
+        ]
+    );
+    assert_eq!(val.size_at_rest(), memory.len());
+    it_reads_what_it_writes(&val);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(size_type(u8))]
+struct TestGenerics<'a, T: Clone, const N: usize> {
+    array: [T; N],
+    list: Vec<T>,
+    cow: std::borrow::Cow<'a, T>,
+}
+
+#[test]
+fn test_generics() {
+    let val = TestGenerics {
+        array: [1_u16, 2, 3],
+        list: vec![4],
+        cow: std::borrow::Cow::Owned(5),
+    };
+    assert_eq!(val.size_at_rest(), 11);
+    it_reads_what_it_writes(&val);
+
+    let val: TestGenerics<TestTree, 1> = TestGenerics {
+        array: [TestTree::Leaf(1)],
+        list: vec![],
+        cow: std::borrow::Cow::Owned(TestTree::Leaf(2)),
+    };
+    it_reads_what_it_writes(&val);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(endian = dynamic, magic(u32 = 0xa1b2_c3d4), context = plod::ByteOrder)]
+struct TestCapture {
+    major: u16,
+    minor: u16,
+    header: TestCaptureRecord,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(endian = dynamic, context = plod::ByteOrder, size_type(u16))]
+struct TestCaptureRecord {
+    length: u32,
+    #[plod(encoding = "utf16")]
+    name: String,
+}
+
+#[test]
+fn test_dynamic_endian() {
+    let val = TestCapture {
+        major: 2,
+        minor: 4,
+        header: TestCaptureRecord {
+            length: 0x10203,
+            name: "a".to_string(),
+        },
+    };
+    let big = [0xa1, 0xb2, 0xc3, 0xd4, 0, 2, 0, 4, 0, 1, 2, 3, 0, 1, 0, b'a'];
+    let little = [0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0, 3, 2, 1, 0, 1, 0, b'a', 0];
+    // the magic value gives the byte order, whatever the context
+    for ctx in [plod::ByteOrder::Big, plod::ByteOrder::Little] {
+        assert_eq!(TestCapture::impl_read_from(&mut &big[..], &ctx, 0).unwrap(), val);
+        assert_eq!(TestCapture::impl_read_from(&mut &little[..], &ctx, 0).unwrap(), val);
+    }
+    // the context gives the byte order on write
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.impl_write_to(&mut memory, &plod::ByteOrder::Big, 0).is_ok());
+    assert_eq!(memory, big);
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.impl_write_to(&mut memory, &plod::ByteOrder::Little, 0).is_ok());
+    assert_eq!(memory, little);
+    assert_eq!(val.size_at_rest(), big.len());
+
+    // without a magic value, only the context gives the byte order
+    let record = TestCaptureRecord::impl_read_from(&mut &big[8..], &plod::ByteOrder::Big, 0);
+    assert_eq!(record.unwrap(), val.header);
+    let record = TestCaptureRecord::impl_read_from(&mut &little[8..], &plod::ByteOrder::Little, 0);
+    assert_eq!(record.unwrap(), val.header);
+    let record = TestCaptureRecord::impl_read_from(&mut &little[8..], &plod::ByteOrder::Big, 0);
+    assert!(record.is_err());
+
+    let bad = [1, 2, 3, 4, 0, 2, 0, 4, 0, 1, 2, 3, 0, 1, 0, b'a'];
+    let err = TestCapture::read_from(&mut &bad[..]).unwrap_err();
+    assert!(matches!(err.kind(), plod::ErrorKind::BadMagic { .. }));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), size_type(u8), big_endian)]
+enum TestBody {
+    #[plod(tag = 1)]
+    Ping(u16),
+    #[plod(tag = 2)]
+    Data(Vec<u8>),
+    #[plod(keep_tag, tag = 3..=5)]
+    Other(u8),
+    #[plod(keep_tag)]
+    Unknown(u8),
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestMessage {
+    msg_type: u8,
+    len: u16,
+    #[plod(tag_from = msg_type, bytes_from = len)]
+    body: TestBody,
+}
+
+#[test]
+fn test_tag_from() {
+    let messages = [
+        (TestMessage { msg_type: 1, len: 2, body: TestBody::Ping(3) }, vec![1, 0, 2, 0, 3]),
+        (TestMessage { msg_type: 2, len: 3, body: TestBody::Data(vec![4, 5]) }, vec![2, 0, 3, 2, 4, 5]),
+        (TestMessage { msg_type: 4, len: 0, body: TestBody::Other(4) }, vec![4, 0, 0]),
+        (TestMessage { msg_type: 9, len: 0, body: TestBody::Unknown(9) }, vec![9, 0, 0]),
+    ];
+    for (val, bytes) in messages {
+        let mut memory: Vec<u8> = Vec::new();
+        assert!(val.write_to(&mut memory).is_ok());
+        assert_eq!(memory, bytes);
+        assert_eq!(val.size_at_rest(), bytes.len());
+        it_reads_what_it_writes(&val);
+    }
+
+    // the tag field must select the variant
+    let val = TestMessage { msg_type: 2, len: 2, body: TestBody::Ping(3) };
+    let mut memory: Vec<u8> = Vec::new();
+    let err = val.write_to(&mut memory).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        plod::ErrorKind::TagMismatch { variant: "Ping", .. }
+    ));
+    assert_eq!(err.path(), "TestMessage.body");
+    assert_eq!(err.offset(), Some(3));
+
+    // so must a retained tag
+    let val = TestMessage { msg_type: 3, len: 0, body: TestBody::Other(4) };
+    let err = val.write_to(&mut Vec::new()).unwrap_err();
+    assert!(matches!(err.kind(), plod::ErrorKind::TagMismatch { variant: "Other", .. }));
+
+    // the body is limited to its size
+    let err = TestMessage::read_from(&mut &[1, 0, 3, 0, 3, 0][..]).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        plod::ErrorKind::SizeMismatch { size_field: "len", expected: 3, found: 2 }
+    ));
+    let err = TestMessage { msg_type: 1, len: 3, body: TestBody::Ping(3) }.write_to(&mut Vec::new()).unwrap_err();
+    assert!(matches!(err.kind(), plod::ErrorKind::SizeMismatch { size_field: "len", .. }));
+
+    // a size bigger than the data is not allocated
+    let err = TestMessage::read_from(&mut &[1, 0xff, 0xff, 0, 3][..]).unwrap_err();
+    assert!(matches!(err.kind(), plod::ErrorKind::UnexpectedEof));
+    assert_eq!(err.path(), "TestMessage.body");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u16), big_endian)]
+enum TestRecord {
+    #[plod(tag = 1)]
+    Known(u16),
+    #[plod(other)]
+    Unknown { tag: u16, raw: Vec<u8> },
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), size_type(u8))]
+enum TestFramed {
+    #[plod(tag = 1)]
+    Known(u8),
+    #[plod(other)]
+    Unknown(u8, #[plod(size_type(u16), big_endian)] Vec<u8>),
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestRecordHeader {
+    kind: u16,
+    len: u8,
+    #[plod(tag_from = kind, bytes_from = len)]
+    record: TestRecord,
+    trailer: u8,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestUnboundedRecord {
+    kind: u16,
+    #[plod(tag_from = kind)]
+    record: TestRecord,
+    trailer: u8,
+}
+
+#[test]
+fn test_other() {
+    // without size, unknown records keep everything left
+    let known = [0, 1, 0, 2];
+    assert_eq!(TestRecord::read_from(&mut &known[..]).unwrap(), TestRecord::Known(2));
+    let unknown = [0, 7, 1, 2, 3];
+    let val = TestRecord::read_from(&mut &unknown[..]).unwrap();
+    assert_eq!(val, TestRecord::Unknown { tag: 7, raw: vec![1, 2, 3] });
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, unknown);
+    assert_eq!(val.size_at_rest(), unknown.len());
+
+    // with a size prefix, the next records can still be read
+    let stream = [9, 0, 2, 1, 2, 1, 3];
+    let vals = <[TestFramed; 2]>::read_from(&mut &stream[..]).unwrap();
+    assert_eq!(vals, [TestFramed::Unknown(9, vec![1, 2]), TestFramed::Known(3)]);
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(vals.write_to(&mut memory).is_ok());
+    assert_eq!(memory, stream);
+    it_reads_what_it_writes(&TestFramed::Unknown(4, vec![5]));
+
+    // with tag_from, the size of the enum field stops the rest of the data before the next field
+    let data = [0, 9, 3, 1, 2, 3, 7];
+    let val = TestRecordHeader::read_from(&mut &data[..]).unwrap();
+    assert_eq!(val.record, TestRecord::Unknown { tag: 9, raw: vec![1, 2, 3] });
+    assert_eq!(val.trailer, 7);
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, data);
+    it_reads_what_it_writes(&TestRecordHeader {
+        kind: 1,
+        len: 2,
+        record: TestRecord::Known(5),
+        trailer: 6,
+    });
+
+    // without it, the next field is taken by the unknown record
+    let err = TestUnboundedRecord::read_from(&mut &[0, 9, 1, 2, 3, 7][..]).unwrap_err();
+    assert!(matches!(err.kind(), plod::ErrorKind::UnexpectedEof));
+    assert_eq!(err.path(), "TestUnboundedRecord.trailer");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestChunk {
+    length: u32,
+    kind: [u8; 4],
+    #[plod(count_from = length)]
+    data: Vec<u8>,
+    #[plod(checksum = crc32, from = kind)]
+    crc: u32,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestIpHeader {
+    version: u8,
+    tos: u8,
+    total_length: u16,
+    id: u16,
+    fragment: u16,
+    ttl: u8,
+    protocol: u8,
+    #[plod(checksum = internet, to = destination)]
+    checksum: u16,
+    source: u32,
+    destination: u32,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(borrow, big_endian)]
+struct TestBorrowedChunk<'a> {
+    #[plod(size_type(u32))]
+    data: &'a [u8],
+    #[plod(checksum = crc32c)]
+    crc: u32,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestFrame(u8, u8, #[plod(checksum = crc16_modbus)] u16);
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestForensicFrame(u8, u8, #[plod(checksum = crc16_modbus, verify = false)] u16);
+
+#[test]
+fn test_checksum() {
+    // PNG IEND chunk, the length is not covered by the CRC
+    let iend = [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82];
+    let val = TestChunk::read_from(&mut &iend[..]).unwrap();
+    assert_eq!(val.crc, 0xae42_6082);
+    let mut memory: Vec<u8> = Vec::new();
+    let chunk = TestChunk { length: 0, kind: *b"IEND", data: vec![], crc: 0 };
+    assert!(chunk.write_to(&mut memory).is_ok());
+    assert_eq!(memory, iend);
+
+    // the embedded checksum counts as zeros
+    let header = [
+        0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0xb8, 0x61, 0xc0, 0xa8, 0x00,
+        0x01, 0xc0, 0xa8, 0x00, 0xc7,
+    ];
+    let val = TestIpHeader::read_from(&mut &header[..]).unwrap();
+    assert_eq!(val.checksum, 0xb861);
+    assert_eq!(val.destination, 0xc0a8_00c7);
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(TestIpHeader { checksum: 0, ..val }.write_to(&mut memory).is_ok());
+    assert_eq!(memory, header);
+    let mut corrupted = header;
+    corrupted[15] = 2;
+    let err = TestIpHeader::read_from(&mut &corrupted[..]).unwrap_err();
+    assert!(matches!(err.kind(), plod::ErrorKind::BadChecksum { algorithm: "internet", .. }));
+    assert_eq!(err.offset(), Some(10));
+    assert_eq!(err.path(), "TestIpHeader.checksum");
+
+    // borrowed data is checked without copy, the size prefix is part of the data field
+    let data = [0, 0, 0, 9, b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9', 0x69, 0x34, 0xcf, 0x6f];
+    let (val, _) = TestBorrowedChunk::parse(&data).unwrap();
+    assert_eq!(val.data, b"123456789");
+    assert!(TestBorrowedChunk::parse(&data[..16]).is_err());
+
+    // the default range is every field before the checksum
+    let frame = [1, 2, 0x81, 0xe1];
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(TestFrame(1, 2, 0).write_to(&mut memory).is_ok());
+    assert_eq!(memory, frame);
+    it_reads_what_it_writes(&TestFrame(3, 4, 0x8300));
+    let err = TestFrame::read_from(&mut &[1, 2, 0, 0][..]).unwrap_err();
+    assert!(matches!(err.kind(), plod::ErrorKind::BadChecksum { .. }));
+    assert_eq!(err.offset(), Some(2));
+    let val = TestForensicFrame::read_from(&mut &[1, 2, 0, 0][..]).unwrap();
+    assert_eq!(val, TestForensicFrame(1, 2, 0));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestLengths {
+    #[plod(size_of_self)]
+    total: u16,
+    #[plod(len_of = payload)]
+    payload_len: u8,
+    #[plod(count_of = items)]
+    n_items: u8,
+    #[plod(bytes_from = payload_len)]
+    payload: Vec<u16>,
+    #[plod(size_type(u8))]
+    items: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), big_endian)]
+enum TestSizedRecord {
+    #[plod(tag = 1)]
+    Text {
+        #[plod(size_of_self)]
+        record_size: u8,
+        #[plod(len_of = text)]
+        len: u8,
+        #[plod(bytes_from = len)]
+        text: String,
+    },
+    #[plod(tag = 2)]
+    Pair(#[plod(size_of_self)] u8, u32),
+}
+
+#[test]
+fn test_size_of() {
+    // stored sizes are ignored on write
+    let val = TestLengths {
+        total: 0,
+        payload_len: 0,
+        n_items: 0,
+        payload: vec![1, 2],
+        items: vec![7, 8, 9],
+    };
+    let data = [0, 12, 4, 3, 0, 1, 0, 2, 3, 7, 8, 9];
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, data);
+    let val = TestLengths::read_from(&mut &data[..]).unwrap();
+    assert_eq!((val.total, val.payload_len, val.n_items), (12, 4, 3));
+    it_reads_what_it_writes(&val);
+
+    // and checked on read
+    let mut corrupted = data;
+    corrupted[3] = 2;
+    let err = TestLengths::read_from(&mut &corrupted[..]).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        plod::ErrorKind::SizeMismatch { size_field: "n_items", expected: 2, found: 3 }
+    ));
+    assert_eq!(err.offset(), Some(8));
+    assert_eq!(err.path(), "TestLengths.items");
+    let mut corrupted = data;
+    corrupted[1] = 13;
+    let err = TestLengths::read_from(&mut &corrupted[..]).unwrap_err();
+    assert!(matches!(err.kind(), plod::ErrorKind::SizeMismatch { size_field: "total", .. }));
+    assert_eq!(err.offset(), Some(0));
+
+    // the size of a variant includes its tag
+    let mut memory: Vec<u8> = Vec::new();
+    let text = TestSizedRecord::Text { record_size: 0, len: 0, text: "hi".to_string() };
+    assert!(text.write_to(&mut memory).is_ok());
+    assert_eq!(memory, [1, 5, 2, b'h', b'i']);
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(TestSizedRecord::Pair(0, 5).write_to(&mut memory).is_ok());
+    assert_eq!(memory, [2, 6, 0, 0, 0, 5]);
+    it_reads_what_it_writes(&TestSizedRecord::Pair(6, 5));
+
+    // a size that doesn't fit its field cannot be written
+    let text = TestSizedRecord::Text { record_size: 0, len: 0, text: "a".repeat(300) };
+    let err = text.write_to(&mut Vec::new()).unwrap_err();
+    assert!(matches!(err.kind(), plod::ErrorKind::InvalidValue { type_name: "u8", .. }));
+}
+
+/// LEB128 unsigned integers
+mod varint {
+    use plod::{PlodRead, PlodWrite, Result};
+
+    pub fn read<R: PlodRead>(from: &mut R, _ctx: &u8, _pos: usize) -> Result<u32> {
+        let mut value = 0;
+        for shift in (0..32).step_by(7) {
+            let mut byte = [0];
+            from.read_exact(&mut byte)?;
+            value |= ((byte[0] & 0x7f) as u32) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(value)
+    }
+
+    pub fn write<W: PlodWrite>(value: &u32, to: &mut W, _ctx: &u8, _pos: usize) -> Result<()> {
+        let mut value = *value;
+        while value >= 0x80 {
+            to.write_all(&[value as u8 | 0x80])?;
+            value >>= 7;
+        }
+        to.write_all(&[value as u8])?;
+        Ok(())
+    }
+
+    pub fn size(value: &u32, _pos: usize) -> usize {
+        (32 - (value | 1).leading_zeros() as usize).div_ceil(7)
+    }
+}
+
+fn unscramble<R: PlodRead>(from: &mut R, key: &u8, _pos: usize) -> Result<u8> {
+    let mut byte = [0];
+    from.read_exact(&mut byte)?;
+    Ok(byte[0] ^ key)
+}
+
+fn scramble<W: PlodWrite>(value: &u8, to: &mut W, key: &u8, _pos: usize) -> Result<()> {
+    to.write_all(&[value ^ key])?;
+    Ok(())
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(context = u8)]
+struct TestCodec {
+    #[plod(with = varint)]
+    length: u32,
+    // the size is the size of u8
+    #[plod(read_with = unscramble, write_with = scramble)]
+    secret: u8,
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+}
+
+#[test]
+fn test_with() {
+    let val = TestCodec {
+        length: 300,
+        secret: 1,
+        data: vec![2],
+    };
+    let data = [0xac, 0x02, 0x54, 1, 2];
+    assert_eq!(val.size_at_rest(), 5);
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.impl_write_to(&mut memory, &0x55, 0).is_ok());
+    assert_eq!(memory, data);
+    let read = TestCodec::impl_read_from(&mut &data[..], &0x55, 0).unwrap();
+    assert_eq!(read, val);
+
+    // errors of custom functions are located like any other
+    let err = TestCodec::read_from(&mut &data[..2]).unwrap_err();
+    assert!(matches!(err.kind(), plod::ErrorKind::UnexpectedEof));
+    assert_eq!(err.offset(), Some(2));
+    assert_eq!(err.path(), "TestCodec.secret");
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum TestMode {
+    Off,
+    On,
+}
+
+impl TryFrom<u8> for TestMode {
+    type Error = ();
+
+    fn try_from(value: u8) -> std::result::Result<Self, ()> {
+        match value {
+            0 => Ok(TestMode::Off),
+            1 => Ok(TestMode::On),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<TestMode> for u8 {
+    fn from(mode: TestMode) -> u8 {
+        mode as u8
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct TestMillis(u32);
+
+impl From<u32> for TestMillis {
+    fn from(value: u32) -> Self {
+        TestMillis(value)
+    }
+}
+
+impl From<TestMillis> for u32 {
+    fn from(millis: TestMillis) -> u32 {
+        millis.0
+    }
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestWire {
+    #[plod(wire = u8, try_from)]
+    mode: TestMode,
+    #[plod(wire = u32, into)]
+    delay: TestMillis,
+    #[plod(wire = u16, try_into)]
+    port: u32,
+}
+
+#[test]
+fn test_wire() {
+    let val = TestWire {
+        mode: TestMode::On,
+        delay: TestMillis(1000),
+        port: 80,
+    };
+    let data = [1, 0, 0, 3, 0xe8, 0, 80];
+    assert_eq!(val.size_at_rest(), 7);
+    let mut memory: Vec<u8> = Vec::new();
+    assert!(val.write_to(&mut memory).is_ok());
+    assert_eq!(memory, data);
+    it_reads_what_it_writes(&val);
+
+    // conversion failures are typed errors
+    let mut invalid = data;
+    invalid[0] = 2;
+    let err = TestWire::read_from(&mut &invalid[..]).unwrap_err();
+    assert!(matches!(err.kind(), plod::ErrorKind::InvalidValue { type_name: "TestMode", .. }));
+    assert_eq!(err.offset(), Some(0));
+    assert_eq!(err.path(), "TestWire.mode");
+    let err = TestWire { port: 70000, ..val }.write_to(&mut Vec::new()).unwrap_err();
+    assert!(matches!(err.kind(), plod::ErrorKind::InvalidConversion { from: "u32", to: "u16" }));
+    assert_eq!(err.offset(), Some(5));
+    assert_eq!(err.path(), "TestWire.port");
+}
+
 // TODO test endianness mix and match
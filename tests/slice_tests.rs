@@ -0,0 +1,69 @@
+// these tests must pass with and without the std feature
+use plod::{ErrorKind, Plod, PlodRead, PlodWrite};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, tag_type(u8))]
+enum Message {
+    #[plod(tag = 1)]
+    Ping(u32),
+    #[plod(tag = 2, size_type(u16))]
+    Data(Vec<u8>),
+}
+
+#[test]
+fn test_slices() {
+    let message = Message::Data(vec![1, 2, 3]);
+    let mut buffer = [0_u8; 8];
+    let mut to = buffer.as_mut_slice();
+    assert!(message.write_to(&mut to).is_ok());
+    assert_eq!(to.len(), 2);
+    assert_eq!(buffer, [2, 0, 3, 1, 2, 3, 0, 0]);
+
+    let mut from = buffer.as_slice();
+    assert_eq!(Message::read_from(&mut from).unwrap(), message);
+    assert_eq!(from, &[0, 0]);
+
+    let err = Message::read_from(&mut &buffer[..5]).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+
+    let err = Message::Ping(1).write_to(&mut &mut buffer[..3]).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::WriteZero));
+
+    let mut memory = Vec::new();
+    assert!(Message::Ping(1).write_to(&mut memory).is_ok());
+    assert_eq!(memory, [1, 0, 0, 0, 1]);
+}
+
+/// Reader and writer that only implement the plod traits
+struct Bytes {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl PlodRead for Bytes {
+    fn read(&mut self, buf: &mut [u8]) -> plod::Result<usize> {
+        let size = buf.len().min(self.data.len() - self.pos);
+        buf[..size].copy_from_slice(&self.data[self.pos..self.pos + size]);
+        self.pos += size;
+        Ok(size)
+    }
+}
+
+impl PlodWrite for Bytes {
+    fn write_all(&mut self, buf: &[u8]) -> plod::Result<()> {
+        self.data.extend_from_slice(buf);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_custom_io() {
+    let message = Message::Data(vec![1, 2, 3]);
+    let mut bytes = Bytes { data: Vec::new(), pos: 0 };
+    assert!(message.write_to(&mut bytes).is_ok());
+    assert_eq!(bytes.data, [2, 0, 3, 1, 2, 3]);
+    assert_eq!(Message::read_from(&mut bytes).unwrap(), message);
+
+    let err = Message::read_from(&mut bytes).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+}